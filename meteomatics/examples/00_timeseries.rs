@@ -35,7 +35,7 @@ async fn main(){
     println!("{:?}", df_ts);
 
     // Do some calculations
-    for col in vec!["t_2m:C", "precip_1h:mm"] {
+    for col in ["t_2m:C", "precip_1h:mm"] {
         let mean: f64 = df_ts[col].mean().unwrap();
         let max: f64 = df_ts[col].max().unwrap();
         let min: f64 = df_ts[col].min().unwrap();
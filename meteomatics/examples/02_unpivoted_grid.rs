@@ -39,7 +39,7 @@ async fn main(){
     println!("{:?}", df_unpivoted);
 
     // Do some calculations
-    for col in vec!["t_2m:C", "precip_1h:mm"] {
+    for col in ["t_2m:C", "precip_1h:mm"] {
         let mean: f64 = df_unpivoted[col].mean().unwrap();
         let max: f64 = df_unpivoted[col].max().unwrap();
         let min: f64 = df_unpivoted[col].min().unwrap();
@@ -47,9 +47,9 @@ async fn main(){
     }
 
     // Do some groupby calculations
-    for col in vec!["t_2m:C", "precip_1h:mm"] {
-        let lat_means = df_unpivoted.groupby(["lat"]).unwrap().select(&[col]).mean().unwrap();
-        let lon_means = df_unpivoted.groupby(["lon"]).unwrap().select(&[col]).mean().unwrap();
+    for col in ["t_2m:C", "precip_1h:mm"] {
+        let lat_means = df_unpivoted.groupby(["lat"]).unwrap().select([col]).mean().unwrap();
+        let lon_means = df_unpivoted.groupby(["lon"]).unwrap().select([col]).mean().unwrap();
         println!("{:?}", lat_means);
         println!("{:?}", lon_means);
     }
@@ -0,0 +1,183 @@
+//! # Cache
+//! This module provides an optional on-disk cache for API responses. Every successful response is
+//! stored gzip-compressed (together with its ```Content-Type```) in a cache directory, keyed by a hash
+//! of the query URL and of the account that sent it. Requesting the same URL again within the time-to-live reads the response from disk
+//! instead of fetching it. This is mainly useful for historical queries, since their data never
+//! changes.
+
+use crate::errors::ConnectorError;
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use reqwest::header::{HeaderMap, HeaderValue, CONTENT_TYPE};
+use reqwest::{Response, ResponseBuilderExt, StatusCode};
+use std::fs;
+use std::io::{Read, Write};
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime};
+use url::Url;
+
+/// Location and time-to-live of the cached responses (see ```APIClient::with_disk_cache()```).
+#[derive(Clone, Debug)]
+pub struct DiskCache {
+    pub dir: PathBuf,
+    pub ttl: Duration,
+    /// Hash of the identity (e.g. the account) the responses belong to (see ```with_scope()```).
+    scope: u64,
+}
+
+/// The version of the format of the cache entries, part of their file names so that entries of an
+/// older format are never read. Version 2 stores the ```Content-Type``` in the first line.
+const ENTRY_VERSION: u32 = 2;
+
+/// A cached response: the body and the ```Content-Type``` header it was received with, which is
+/// needed to decode the charset and to recognize error pages (see ```util::read_csv_body()```).
+#[derive(Clone, Debug, PartialEq)]
+pub struct CacheEntry {
+    pub content_type: Option<String>,
+    pub body: Vec<u8>,
+}
+
+impl CacheEntry {
+    /// Rebuilds the response (status 200) with the ```Content-Type``` header of the entry.
+    pub fn into_response(self, url: Url) -> Response {
+        let mut headers = HeaderMap::new();
+        if let Some(value) = self.content_type.and_then(|ct| HeaderValue::from_str(&ct).ok()) {
+            headers.insert(CONTENT_TYPE, value);
+        }
+        response_from_parts(url, StatusCode::OK, headers, self.body)
+    }
+}
+
+/// The offset basis of the 64 bit FNV-1a hash.
+const FNV_OFFSET: u64 = 0xcbf29ce484222325;
+
+/// Continues the 64 bit FNV-1a hash ```hash``` with ```bytes```. Unlike the hasher of the standard
+/// library, the hash is stable across compiler versions and restarts.
+fn fnv1a(mut hash: u64, bytes: &[u8]) -> u64 {
+    for byte in bytes {
+        hash ^= *byte as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    hash
+}
+
+impl DiskCache {
+    /// Creates a new cache in the directory ```dir```. The directory is created when the first
+    /// response is stored.
+    pub fn new(dir: &str, ttl: Duration) -> Self {
+        Self { dir: PathBuf::from(dir), ttl, scope: FNV_OFFSET }
+    }
+
+    /// Separates the entries of different identities (e.g. accounts or tokens) that share the
+    /// directory, such that the response of one account is never served to another one. Only a hash
+    /// of the identity is kept.
+    pub fn with_scope(mut self, identity: &str) -> Self {
+        self.scope = fnv1a(FNV_OFFSET, identity.as_bytes());
+        self
+    }
+
+    /// Returns the path of the cache file for the URL. The name is a FNV-1a hash of the scope and
+    /// the URL, followed by the version of the entry format.
+    pub fn path(&self, url: &Url) -> PathBuf {
+        let hash = fnv1a(self.scope, url.as_str().as_bytes());
+        self.dir.join(format!("{:016x}.v{}.gz", hash, ENTRY_VERSION))
+    }
+
+    /// Reads the cached response for the URL. Returns ```None``` if there is no cache entry, if the
+    /// entry is older than the time-to-live or if the entry cannot be read.
+    pub fn load(&self, url: &Url) -> Option<CacheEntry> {
+        let path = self.path(url);
+        let modified = fs::metadata(&path).ok()?.modified().ok()?;
+        let age = SystemTime::now().duration_since(modified).unwrap_or_default();
+        if age > self.ttl {
+            return None;
+        }
+
+        let mut entry = Vec::new();
+        GzDecoder::new(fs::File::open(&path).ok()?).read_to_end(&mut entry).ok()?;
+        // The first line holds the content type (empty if there was none), the rest is the body.
+        let newline = entry.iter().position(|b| *b == b'\n')?;
+        let content_type = String::from_utf8(entry[..newline].to_vec()).ok()?;
+        Some(CacheEntry {
+            content_type: Some(content_type).filter(|ct| !ct.is_empty()),
+            body: entry.split_off(newline + 1),
+        })
+    }
+
+    /// Stores the gzip-compressed response body and its content type for the URL.
+    pub fn store(&self, url: &Url, content_type: Option<&str>, body: &[u8]) -> Result<(), ConnectorError> {
+        // A header value never contains a line break, but a broken entry must not be possible.
+        let content_type = content_type.filter(|ct| !ct.contains(['\r', '\n'])).unwrap_or("");
+        fs::create_dir_all(&self.dir)?;
+        // Write to a temporary file first so that a concurrent read never sees a partial entry.
+        let path = self.path(url);
+        let tmp_path = path.with_extension("gz.tmp");
+        let mut encoder = GzEncoder::new(fs::File::create(&tmp_path)?, Compression::default());
+        encoder.write_all(content_type.as_bytes())?;
+        encoder.write_all(b"\n")?;
+        encoder.write_all(body)?;
+        encoder.finish()?;
+        fs::rename(&tmp_path, &path)?;
+        Ok(())
+    }
+}
+
+/// Creates a [`reqwest::Response`] with status 200 and the given body (e.g. from a cache hit).
+pub fn response_from_body(url: Url, body: Vec<u8>) -> Response {
+    // Building a response with only a status and a body cannot fail.
+    let response = http::Response::builder().url(url).body(body).unwrap();
+    Response::from(response)
+}
+
+/// Creates a [`reqwest::Response`] from its parts (e.g. after the body has been read to store it).
+pub fn response_from_parts(url: Url, status: StatusCode, headers: HeaderMap, body: impl Into<reqwest::Body>) -> Response {
+    // The status is already valid, hence building the response cannot fail.
+    let mut response = http::Response::builder().url(url).status(status).body(body).unwrap();
+    *response.headers_mut() = headers;
+    Response::from(response)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::cache::DiskCache;
+    use std::time::Duration;
+
+    #[test]
+    fn check_store_and_load() {
+        let dir = std::env::temp_dir().join(format!("meteomatics_cache_{}", std::process::id()));
+        let cache = DiskCache::new(dir.to_str().unwrap(), Duration::from_secs(3600));
+        let url = url::Url::parse("https://api.meteomatics.com/2020-01-01T00:00:00Z/t_2m:C/47.4,9.3/csv").unwrap();
+        let other = url::Url::parse("https://api.meteomatics.com/2020-01-02T00:00:00Z/t_2m:C/47.4,9.3/csv").unwrap();
+
+        assert_ne!(cache.path(&url), cache.path(&other));
+        assert!(cache.load(&url).is_none());
+
+        cache.store(&url, Some("text/csv; charset=utf-8"), b"validdate;t_2m:C\n2020-01-01T00:00:00Z;1.5\n").unwrap();
+        let entry = cache.load(&url).unwrap();
+        assert_eq!(entry.content_type.as_deref(), Some("text/csv; charset=utf-8"));
+        assert_eq!(entry.body, b"validdate;t_2m:C\n2020-01-01T00:00:00Z;1.5\n".to_vec());
+        assert!(cache.load(&other).is_none());
+
+        // The content type is restored on the response.
+        let response = entry.into_response(url.clone());
+        assert_eq!(response.status(), reqwest::StatusCode::OK);
+        assert_eq!(response.headers()[reqwest::header::CONTENT_TYPE], "text/csv; charset=utf-8");
+
+        // An entry without a content type
+        cache.store(&other, None, b"").unwrap();
+        assert_eq!(cache.load(&other).unwrap(), crate::cache::CacheEntry { content_type: None, body: Vec::new() });
+
+        // Expired entries are ignored
+        let expired = DiskCache { ttl: Duration::from_secs(0), ..cache.clone() };
+        std::thread::sleep(Duration::from_millis(10));
+        assert!(expired.load(&url).is_none());
+
+        // Entries of another identity are not shared
+        let scoped = cache.clone().with_scope("basic:other_user:other_password");
+        assert_ne!(scoped.path(&url), cache.path(&url));
+        assert!(scoped.load(&url).is_none());
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
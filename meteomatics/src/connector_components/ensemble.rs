@@ -0,0 +1,68 @@
+//! # Ensemble
+//! Typed selection of the ensemble members of a probabilistic forecast.
+//! ```rust, no_run
+//! use meteomatics::connector_components::EnsembleSpec;
+//!
+//! let optionals: Option<Vec<String>> = Some(vec![EnsembleSpec::Quantile(0.9).into()]);
+//! ```
+
+use std::fmt;
+
+/// The ensemble members to request from an ensemble model (e.g. "ecmwf-ens"). The selection is
+/// rendered as the ```ens_select``` optional of the API.
+#[derive(Clone, Debug, PartialEq)]
+pub enum EnsembleSpec {
+    /// All members of the ensemble.
+    All,
+    /// A single member (0 is the control run).
+    Member(u32),
+    /// A quantile of the ensemble distribution (between 0 and 1, e.g. 0.9).
+    Quantile(f64),
+}
+
+impl EnsembleSpec {
+    /// Returns the suffix the API appends to the parameter name in the CSV header for this selection
+    /// (e.g. "m3" for ```Member(3)```). ```All``` has no single suffix.
+    pub fn label(&self) -> Option<String> {
+        match self {
+            EnsembleSpec::All => None,
+            EnsembleSpec::Member(n) => Some(format!("m{}", n)),
+            EnsembleSpec::Quantile(p) => Some(format!("q{}", p)),
+        }
+    }
+}
+
+/// This Display Trait renders the member selection as an optional parameter for the query URL
+/// (e.g. "ens_select=member:3").
+impl fmt::Display for EnsembleSpec {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            EnsembleSpec::All => write!(f, "ens_select=all"),
+            EnsembleSpec::Member(n) => write!(f, "ens_select=member:{}", n),
+            EnsembleSpec::Quantile(p) => write!(f, "ens_select=quantile{}", p),
+        }
+    }
+}
+
+impl From<EnsembleSpec> for String {
+    fn from(spec: EnsembleSpec) -> Self {
+        spec.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::connector_components::EnsembleSpec;
+
+    #[test]
+    fn check_ensemble_spec_string() {
+        assert_eq!(EnsembleSpec::All.to_string(), "ens_select=all");
+        assert_eq!(EnsembleSpec::Member(0).to_string(), "ens_select=member:0");
+        assert_eq!(EnsembleSpec::Member(12).to_string(), "ens_select=member:12");
+        assert_eq!(EnsembleSpec::Quantile(0.9).to_string(), "ens_select=quantile0.9");
+
+        assert_eq!(EnsembleSpec::All.label(), None);
+        assert_eq!(EnsembleSpec::Member(12).label(), Some(String::from("m12")));
+        assert_eq!(EnsembleSpec::Quantile(0.25).label(), Some(String::from("q0.25")));
+    }
+}
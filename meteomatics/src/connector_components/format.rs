@@ -0,0 +1,95 @@
+//! # Format
+//! Typed selection of the output format of a query, i.e. the last path segment of the query URL.
+//! ```rust, no_run
+//! use meteomatics::connector_components::Format;
+//!
+//! assert_eq!(Format::NetCdf.to_string(), "netcdf");
+//! let format: Format = "png".parse().unwrap();
+//!
+//! // Formats unknown to the connector are passed on to the API as they are.
+//! let format: Format = "html".parse().unwrap();
+//! assert_eq!(format, Format::Other(String::from("html")));
+//! ```
+
+use crate::errors::ConnectorError;
+use std::fmt;
+use std::str::FromStr;
+
+/// The output format requested from the API.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Format {
+    /// Semicolon separated values (used for all DataFrame queries).
+    Csv,
+    /// JSON document.
+    Json,
+    /// NetCDF file (grid time series).
+    NetCdf,
+    /// PNG image (grid).
+    Png,
+    /// GeoTIFF image (grid).
+    GeoTiff,
+    /// GRIB file (grid).
+    Grib,
+    /// Any other format supported by the API, passed on in the query URL as it is.
+    Other(String),
+}
+
+/// This Display Trait renders the format as expected by the API in the query URL (e.g. "netcdf").
+impl fmt::Display for Format {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let format = match self {
+            Format::Csv => "csv",
+            Format::Json => "json",
+            Format::NetCdf => "netcdf",
+            Format::Png => "png",
+            Format::GeoTiff => "geotiff",
+            Format::Grib => "grib",
+            Format::Other(format) => format,
+        };
+        write!(f, "{}", format)
+    }
+}
+
+/// Parses the name of the format as used in the query URL (case insensitive). Names that are not
+/// known to the connector become [`Format::Other`]; only an empty name is rejected.
+impl FromStr for Format {
+    type Err = ConnectorError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "csv" => Ok(Format::Csv),
+            "json" => Ok(Format::Json),
+            "netcdf" => Ok(Format::NetCdf),
+            "png" => Ok(Format::Png),
+            "geotiff" => Ok(Format::GeoTiff),
+            "grib" => Ok(Format::Grib),
+            "" => Err(ConnectorError::LibraryError(String::from("empty format"))),
+            _ => Ok(Format::Other(s.to_string())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::connector_components::Format;
+
+    #[test]
+    fn check_format_string() {
+        let formats = [
+            (Format::Csv, "csv"),
+            (Format::Json, "json"),
+            (Format::NetCdf, "netcdf"),
+            (Format::Png, "png"),
+            (Format::GeoTiff, "geotiff"),
+            (Format::Grib, "grib"),
+        ];
+        for (format, name) in formats {
+            assert_eq!(format.to_string(), name);
+            assert_eq!(name.parse::<Format>().unwrap(), format);
+        }
+        assert_eq!("NetCDF".parse::<Format>().unwrap(), Format::NetCdf);
+        assert_eq!("html".parse::<Format>().unwrap(), Format::Other(String::from("html")));
+        assert_eq!(Format::Other(String::from("html")).to_string(), "html");
+        assert!("".parse::<Format>().is_err());
+    }
+}
@@ -0,0 +1,59 @@
+//! # Interpolation
+//! Typed selection of the on-the-fly interpolation method of the API.
+//! ```rust, no_run
+//! use meteomatics::connector_components::Interpolation;
+//!
+//! let optionals: Option<Vec<String>> = Some(vec![Interpolation::GradientInterpolation.into()]);
+//! ```
+
+use std::fmt;
+
+/// The interpolation method used by the API to derive values at the requested locations. Use
+/// ```Other``` for methods that are not (yet) covered by a dedicated variant.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Interpolation {
+    /// Interpolation that takes the horizontal and vertical gradients into account.
+    GradientInterpolation,
+    /// Value of the closest grid point.
+    NearestNeighbor,
+    /// Interpolation as used by the SQL interface of the API.
+    SqlInterpolation,
+    /// Any other method, given by its name in the API (e.g. "bilinear").
+    Other(String),
+}
+
+/// This Display Trait renders the interpolation method as an optional parameter for the query URL
+/// (e.g. "interpolate=gradient").
+impl fmt::Display for Interpolation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let method = match self {
+            Interpolation::GradientInterpolation => "gradient",
+            Interpolation::NearestNeighbor => "nearest_neighbor",
+            Interpolation::SqlInterpolation => "sql",
+            Interpolation::Other(method) => method,
+        };
+        write!(f, "interpolate={}", method)
+    }
+}
+
+impl From<Interpolation> for String {
+    fn from(interpolation: Interpolation) -> Self {
+        interpolation.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::connector_components::Interpolation;
+
+    #[test]
+    fn check_interpolation_string() {
+        assert_eq!(Interpolation::GradientInterpolation.to_string(), "interpolate=gradient");
+        assert_eq!(Interpolation::NearestNeighbor.to_string(), "interpolate=nearest_neighbor");
+        assert_eq!(Interpolation::SqlInterpolation.to_string(), "interpolate=sql");
+        assert_eq!(Interpolation::Other(String::from("bilinear")).to_string(), "interpolate=bilinear");
+
+        let optional: String = Interpolation::GradientInterpolation.into();
+        assert_eq!(optional, "interpolate=gradient");
+    }
+}
@@ -0,0 +1,99 @@
+//! # Level
+//! Typed selection of the vertical level of a parameter (e.g. for upper-air parameters).
+//! ```rust, no_run
+//! use meteomatics::connector_components::Level;
+//!
+//! let parameter = Level::PressureHpa(500.0).parameter("t", "C").unwrap();
+//! assert_eq!(parameter, "t_500hPa:C");
+//! ```
+
+use crate::errors::ConnectorError;
+use crate::util::validate_parameter;
+use std::fmt;
+
+/// The vertical level of a parameter, rendered as the level token of the parameter name (e.g. "2m"
+/// in "t_2m:C" or "500hPa" in "t_500hPa:C").
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Level {
+    /// Height above ground in meters (negative for depths below ground). Heights that are not whole
+    /// meters are rendered in centimeters (e.g. 0.5 m as "50cm").
+    HeightM(f64),
+    /// Pressure level in hPa (whole hPa only).
+    PressureHpa(f64),
+    /// The surface of the earth.
+    Surface,
+}
+
+impl Level {
+    /// Returns the parameter name for the given base name and unit at this level (e.g. "t" and "C"
+    /// give "t_500hPa:C" for ```PressureHpa(500.0)```). Fails for levels that the API cannot
+    /// represent (e.g. fractions of a centimeter or hPa) and for malformed names (see
+    /// [`validate_parameter`]).
+    ///
+    /// # Arguments
+    ///
+    /// * `base` - The name of the parameter without the level (e.g. "t" or "wind_speed").
+    /// * `unit` - The unit of the parameter (e.g. "C" or "ms").
+    ///
+    pub fn parameter(&self, base: &str, unit: &str) -> Result<String, ConnectorError> {
+        let token = self.token()?;
+        let parameter = format!("{}_{}:{}", base, token, unit);
+        validate_parameter(&parameter)?;
+        Ok(parameter)
+    }
+
+    /// The level token of the parameter name (e.g. "500hPa").
+    fn token(&self) -> Result<String, ConnectorError> {
+        let whole = |value: f64| (value.fract() == 0.0 && value.is_finite()).then_some(value as i64);
+        match *self {
+            Level::HeightM(m) => match (whole(m), whole((m * 100.0 * 1e6).round() / 1e6)) {
+                (Some(m), _) => Ok(format!("{}m", m)),
+                (None, Some(cm)) => Ok(format!("{}cm", cm)),
+                _ => Err(ConnectorError::LibraryError(format!("height {} m is not a whole number of cm", m))),
+            },
+            Level::PressureHpa(hpa) => match whole(hpa) {
+                Some(hpa) if hpa > 0 => Ok(format!("{}hPa", hpa)),
+                _ => Err(ConnectorError::LibraryError(format!("pressure level {} hPa is not a positive whole number", hpa))),
+            },
+            Level::Surface => Ok(String::from("sfc")),
+        }
+    }
+}
+
+/// This Display Trait renders the level token (e.g. "500hPa"), or the raw value for levels the API
+/// cannot represent.
+impl fmt::Display for Level {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match (self.token(), self) {
+            (Ok(token), _) => write!(f, "{}", token),
+            (Err(_), Level::HeightM(m)) => write!(f, "{}m", m),
+            (Err(_), Level::PressureHpa(hpa)) => write!(f, "{}hPa", hpa),
+            (Err(_), Level::Surface) => write!(f, "sfc"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::connector_components::Level;
+
+    #[test]
+    fn check_level_parameter() {
+        assert_eq!(Level::PressureHpa(500.0).parameter("t", "C").unwrap(), "t_500hPa:C");
+        assert_eq!(Level::PressureHpa(850.0).parameter("wind_speed", "ms").unwrap(), "wind_speed_850hPa:ms");
+        assert_eq!(Level::HeightM(2.0).parameter("t", "C").unwrap(), "t_2m:C");
+        assert_eq!(Level::HeightM(100.0).parameter("wind_speed", "kmh").unwrap(), "wind_speed_100m:kmh");
+        assert_eq!(Level::HeightM(0.5).parameter("t", "C").unwrap(), "t_50cm:C");
+        assert_eq!(Level::HeightM(-1.5).parameter("soil_moisture_index", "idx").unwrap(), "soil_moisture_index_-150cm:idx");
+        assert_eq!(Level::Surface.parameter("t", "K").unwrap(), "t_sfc:K");
+        assert_eq!(Level::PressureHpa(500.0).to_string(), "500hPa");
+
+        // Levels the API cannot represent and malformed names
+        assert!(Level::PressureHpa(500.5).parameter("t", "C").is_err());
+        assert!(Level::PressureHpa(0.0).parameter("t", "C").is_err());
+        assert!(Level::HeightM(0.125).parameter("t", "C").is_err());
+        assert!(Level::HeightM(f64::NAN).parameter("t", "C").is_err());
+        assert!(Level::HeightM(2.0).parameter("t 2", "C").is_err());
+        assert!(Level::HeightM(2.0).parameter("t", "").is_err());
+    }
+}
@@ -0,0 +1,52 @@
+//! # Missing data
+//! Typed selection of how the API handles points in time without data.
+//! ```rust, no_run
+//! use meteomatics::connector_components::MissingDataPolicy;
+//!
+//! let optionals: Option<Vec<String>> = Some(vec![MissingDataPolicy::FillNull.into()]);
+//! ```
+
+use std::fmt;
+
+/// How points in time and locations without data are returned. With ```Omit``` the rows are left
+/// out, so the number of rows can vary between queries. With ```FillNull``` every requested point
+/// in time and location has a row, with missing values where there is no data (see
+/// ```query_time_series_with_policy()```).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MissingDataPolicy {
+    /// Leave out the rows without data.
+    Omit,
+    /// Return a row with missing values for every requested point in time and location.
+    FillNull,
+}
+
+/// This Display Trait renders the policy as an optional parameter for the query URL
+/// (e.g. "on_invalid=fill").
+impl fmt::Display for MissingDataPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MissingDataPolicy::Omit => write!(f, "on_invalid=omit"),
+            MissingDataPolicy::FillNull => write!(f, "on_invalid=fill"),
+        }
+    }
+}
+
+impl From<MissingDataPolicy> for String {
+    fn from(policy: MissingDataPolicy) -> Self {
+        policy.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::connector_components::MissingDataPolicy;
+
+    #[test]
+    fn check_missing_data_policy_string() {
+        assert_eq!(MissingDataPolicy::Omit.to_string(), "on_invalid=omit");
+        assert_eq!(MissingDataPolicy::FillNull.to_string(), "on_invalid=fill");
+
+        let optional: String = MissingDataPolicy::FillNull.into();
+        assert_eq!(optional, "on_invalid=fill");
+    }
+}
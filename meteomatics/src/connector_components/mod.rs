@@ -0,0 +1,21 @@
+//! # Connector components
+//! This module bundles typed building blocks for the optional parameters of a query (e.g. the
+//! interpolation method, the ensemble members or the output format). Every component implements ```Display``` and
+//! converts into the ```String``` expected in the ```optionals``` of the query methods. The
+//! [`Level`] builds the level token of parameter names instead (e.g. "t_500hPa:C").
+
+pub mod ensemble;
+pub mod format;
+pub mod interpolation;
+pub mod level;
+pub mod missing_data;
+pub mod png;
+pub mod request_options;
+
+pub use ensemble::EnsembleSpec;
+pub use format::Format;
+pub use interpolation::Interpolation;
+pub use level::Level;
+pub use missing_data::MissingDataPolicy;
+pub use png::PngOptions;
+pub use request_options::RequestOptions;
@@ -0,0 +1,65 @@
+//! # PNG
+//! Typed styling options for PNG grid images (see ```APIClient::query_grid_png_styled()```).
+//! ```rust, no_run
+//! use meteomatics::connector_components::PngOptions;
+//!
+//! let style = PngOptions { colormap: Some(String::from("blue_magenta")), min: Some(-10.0), max: Some(30.0) };
+//! let optionals: Option<Vec<String>> = Some(style.into());
+//! ```
+
+/// The color scale of a PNG grid image. Options that are ```None``` are left to the API's default
+/// style.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct PngOptions {
+    /// Name of the colormap of the API (e.g. "blue_magenta").
+    pub colormap: Option<String>,
+    /// The value at the lower end of the color scale.
+    pub min: Option<f64>,
+    /// The value at the upper end of the color scale.
+    pub max: Option<f64>,
+}
+
+impl PngOptions {
+    /// Renders the options as optional parameters for the query URL, in the order colormap, min and
+    /// max (e.g. ```["colormap=blue_magenta", "min=-10", "max=30"]```).
+    pub fn to_optionals(&self) -> Vec<String> {
+        let mut optionals = Vec::new();
+        if let Some(colormap) = &self.colormap {
+            optionals.push(format!("colormap={}", colormap));
+        }
+        if let Some(min) = self.min {
+            optionals.push(format!("min={}", min));
+        }
+        if let Some(max) = self.max {
+            optionals.push(format!("max={}", max));
+        }
+        optionals
+    }
+}
+
+impl From<PngOptions> for Vec<String> {
+    fn from(options: PngOptions) -> Self {
+        options.to_optionals()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::connector_components::PngOptions;
+    use chrono::{TimeZone, Utc};
+
+    #[tokio::test]
+    async fn check_png_options_fragment() {
+        let style = PngOptions { colormap: Some(String::from("blue_magenta")), min: Some(-10.0), max: Some(30.5) };
+        assert_eq!(style.to_optionals(), vec!["colormap=blue_magenta", "min=-10", "max=30.5"]);
+        assert!(PngOptions::default().to_optionals().is_empty());
+
+        let mut optionals = vec![String::from("model=mix")];
+        optionals.extend(style.to_optionals());
+        let date = Utc.ymd(1989, 11, 9).and_hms(18, 0, 0);
+        let specs = crate::util::build_grid_query_specs(
+            &date, &String::from("t_2m:C"), "47.8,5.9_45.8,10.5:0.01,0.01", &Some(optionals), crate::connector_components::Format::Png
+        ).await;
+        assert!(specs.ends_with("/png?model=mix&colormap=blue_magenta&min=-10&max=30.5"));
+    }
+}
@@ -0,0 +1,102 @@
+//! # Request options
+//! Typed collection of the common optional parameters of a query. The options are rendered to the
+//! ```optionals``` of the query methods, so they can be combined with other components.
+//! ```rust, no_run
+//! use meteomatics::connector_components::{Interpolation, RequestOptions};
+//!
+//! let options = RequestOptions {
+//!     model: Some(String::from("mix")),
+//!     calibrated: Some(true),
+//!     interpolate: Some(Interpolation::GradientInterpolation),
+//!     ..Default::default()
+//! };
+//! let optionals: Option<Vec<String>> = Some(options.into());
+//! ```
+
+use crate::connector_components::Interpolation;
+
+/// The common optional parameters of a query. Options that are ```None``` are not sent, i.e. the
+/// API's default applies.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct RequestOptions {
+    /// The data source (e.g. "mix-obs").
+    pub source: Option<String>,
+    /// The model (e.g. "mix" or "ecmwf-ifs").
+    pub model: Option<String>,
+    /// Whether the values are calibrated with observations.
+    pub calibrated: Option<bool>,
+    /// The interpolation method (see [`Interpolation`]).
+    pub interpolate: Option<Interpolation>,
+    /// The timeout of the request on the server in seconds.
+    pub timeout: Option<u64>,
+    /// Whether the server may answer from its cache.
+    pub cache: Option<bool>,
+}
+
+impl RequestOptions {
+    /// Renders the options as optional parameters for the query URL, in the order of the fields
+    /// (e.g. ```["model=mix", "calibrated=true"]```).
+    pub fn to_optionals(&self) -> Vec<String> {
+        let mut optionals = Vec::new();
+        if let Some(source) = &self.source {
+            optionals.push(format!("source={}", source));
+        }
+        if let Some(model) = &self.model {
+            optionals.push(format!("model={}", model));
+        }
+        if let Some(calibrated) = self.calibrated {
+            optionals.push(format!("calibrated={}", calibrated));
+        }
+        if let Some(interpolate) = &self.interpolate {
+            optionals.push(interpolate.to_string());
+        }
+        if let Some(timeout) = self.timeout {
+            optionals.push(format!("timeout={}", timeout));
+        }
+        if let Some(cache) = self.cache {
+            optionals.push(format!("cache={}", cache));
+        }
+        optionals
+    }
+
+    /// Renders optional options to the ```optionals``` of the query methods (```None``` if there are
+    /// no options to send).
+    pub fn optionals(options: &Option<RequestOptions>) -> Option<Vec<String>> {
+        options
+            .as_ref()
+            .map(|options| options.to_optionals())
+            .filter(|optionals| !optionals.is_empty())
+    }
+}
+
+impl From<RequestOptions> for Vec<String> {
+    fn from(options: RequestOptions) -> Self {
+        options.to_optionals()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::connector_components::{Interpolation, RequestOptions};
+
+    #[test]
+    fn check_request_options_string() {
+        let options = RequestOptions {
+            source: Some(String::from("mix-obs")),
+            model: Some(String::from("mix")),
+            calibrated: Some(true),
+            interpolate: Some(Interpolation::GradientInterpolation),
+            timeout: Some(300),
+            cache: Some(false),
+        };
+        assert_eq!(
+            options.to_optionals().join("&"),
+            "source=mix-obs&model=mix&calibrated=true&interpolate=gradient&timeout=300&cache=false"
+        );
+
+        let partial = RequestOptions { model: Some(String::from("ecmwf-ifs")), ..Default::default() };
+        assert_eq!(RequestOptions::optionals(&Some(partial)), Some(vec![String::from("model=ecmwf-ifs")]));
+        assert_eq!(RequestOptions::optionals(&Some(RequestOptions::default())), None);
+        assert_eq!(RequestOptions::optionals(&None), None);
+    }
+}
@@ -2,21 +2,31 @@ use thiserror::Error;
 
 #[derive(Error, Debug)]
 pub enum ConnectorError {
-    /// ReqwestError.
-    #[error("ReqwestError error")]
-    ReqwestError,
+    /// ReqwestError. Contains the underlying error of reqwest, which is also the ```source()``` of this
+    /// error (e.g. to check ```is_timeout()``` or ```is_connect()```).
+    #[error("ReqwestError error: `{0}`")]
+    ReqwestError(#[from] reqwest::Error),
 
     /// HTTP response error.
     #[error("HTTP error: `{0}`, `{1}`, {2}`")]
     HttpError(String, String, reqwest::StatusCode),
 
+    /// The API rejected the credentials (HTTP 401 or 403).
+    #[error("Authentication error: `{0}`")]
+    AuthError(String),
+
+    /// The API rejected the request because too many requests were sent (HTTP 429). Contains the
+    /// time to wait before the next request as requested by the server, if specified.
+    #[error("Rate limited, retry after: `{retry_after:?}`")]
+    RateLimited { retry_after: Option<std::time::Duration> },
+
     /// Library error.
     #[error("Library error: `{0}`")]
     LibraryError(String),
 
-    /// Polars error.
-    #[error("Polars error")]
-    PolarsError,
+    /// Polars error. Contains the message of the polars error.
+    #[error("Polars error: `{0}`")]
+    PolarsError(String),
 
     /// Generic error.
     #[error(transparent)]
@@ -31,10 +41,25 @@ pub enum ConnectorError {
     FileIOError
 }
 
+impl ConnectorError {
+    /// Returns whether sending the same request again may succeed: true for network errors (timeouts,
+    /// failed connections or failed requests), rate limits and server errors (HTTP 5xx), false for
+    /// client errors (HTTP 4xx), malformed requests (e.g. an invalid URL) and for errors of this
+    /// library (e.g. parsing or file i/o).
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            ConnectorError::ReqwestError(e) => e.is_timeout() || e.is_connect() || e.is_request(),
+            ConnectorError::RateLimited { .. } => true,
+            ConnectorError::HttpError(_, _, status) => status.is_server_error(),
+            _ => false,
+        }
+    }
+}
+
 
 impl From<polars::error::PolarsError> for ConnectorError {
-    fn from(_: polars::error::PolarsError) -> Self {
-        ConnectorError::PolarsError
+    fn from(e: polars::error::PolarsError) -> Self {
+        ConnectorError::PolarsError(e.to_string())
     }
 }
 
@@ -44,14 +69,49 @@ impl From<url::ParseError> for ConnectorError {
     }
 }
 
-impl From<reqwest::Error> for ConnectorError {
-    fn from(_: reqwest::Error) -> Self {
-        ConnectorError::ReqwestError
-    }
-}
-
 impl From<std::io::Error> for ConnectorError {
     fn from(_: std::io::Error) -> Self {
         ConnectorError::FileIOError
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::errors::ConnectorError;
+    use polars::prelude::*;
+
+    fn missing_column(df: &DataFrame) -> std::result::Result<(), ConnectorError> {
+        df.column("t_2m:C")?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn check_is_retryable() {
+        let http_error = |status: reqwest::StatusCode| {
+            ConnectorError::HttpError(status.to_string(), String::new(), status)
+        };
+        // Nothing listens on the port of a dropped listener, so the connection is refused.
+        let addr = std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap();
+        let connect_error = reqwest::get(format!("http://{}", addr)).await.unwrap_err();
+        assert!(ConnectorError::ReqwestError(connect_error).is_retryable());
+        let builder_error = reqwest::Client::new().get("not a url").build().unwrap_err();
+        assert!(!ConnectorError::ReqwestError(builder_error).is_retryable());
+        assert!(ConnectorError::RateLimited { retry_after: None }.is_retryable());
+        assert!(http_error(reqwest::StatusCode::INTERNAL_SERVER_ERROR).is_retryable());
+        assert!(http_error(reqwest::StatusCode::SERVICE_UNAVAILABLE).is_retryable());
+        assert!(!http_error(reqwest::StatusCode::BAD_REQUEST).is_retryable());
+        assert!(!http_error(reqwest::StatusCode::UNAUTHORIZED).is_retryable());
+        assert!(!http_error(reqwest::StatusCode::NOT_FOUND).is_retryable());
+        assert!(!ConnectorError::ParseError.is_retryable());
+        assert!(!ConnectorError::FileIOError.is_retryable());
+    }
+
+    #[test]
+    fn check_polars_error_message() {
+        let df = df!("validdate" => &["1989-11-09T18:00:00Z"]).unwrap();
+        match missing_column(&df) {
+            Err(ConnectorError::PolarsError(msg)) => assert!(msg.contains("t_2m:C")),
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,361 @@
+//! # GeoParquet
+//! This module encodes a query result as a GeoParquet file (see ```util::df_to_geoparquet()```). The
+//! file holds a single row group with one uncompressed, PLAIN encoded data page per column. The
+//! ```lat```/```lon``` columns are additionally stored as WKB points in a ```geometry``` column, which is
+//! described by the GeoParquet metadata under the "geo" key of the file.
+//!
+//! The Parquet metadata structures are serialized with ```parquet-format-safe```; the Parquet writers
+//! of polars and of the ```parquet``` crate are not available for the versions of polars and chrono
+//! used by this crate.
+
+use crate::errors::ConnectorError;
+use parquet_format_safe::thrift::protocol::TCompactOutputProtocol;
+use parquet_format_safe::{
+    ColumnChunk, ColumnMetaData, CompressionCodec, ConvertedType, DataPageHeader, Encoding,
+    FieldRepetitionType, FileMetaData, KeyValue, LogicalType, PageHeader, PageType, RowGroup,
+    SchemaElement, StringType, Type,
+};
+use polars::prelude::*;
+
+/// The magic bytes at the start and at the end of a Parquet file.
+const MAGIC: &[u8] = b"PAR1";
+
+/// The name of the WKB geometry column.
+pub(crate) const GEOMETRY_COLUMN: &str = "geometry";
+
+/// Encodes a point as 2D Well-Known Binary (WKB, little endian) with the longitude as x and the
+/// latitude as y coordinate.
+pub(crate) fn point_to_wkb(lat: f64, lon: f64) -> Vec<u8> {
+    let mut wkb = Vec::with_capacity(21);
+    wkb.push(1u8); // little endian
+    wkb.extend_from_slice(&1u32.to_le_bytes()); // geometry type: point
+    wkb.extend_from_slice(&lon.to_le_bytes());
+    wkb.extend_from_slice(&lat.to_le_bytes());
+    wkb
+}
+
+/// The values of a column in one of the physical types written by this module. Missing values are
+/// ```None```.
+enum Values {
+    Boolean(Vec<Option<bool>>),
+    Int64(Vec<Option<i64>>),
+    Double(Vec<Option<f64>>),
+    String(Vec<Option<String>>),
+    Binary(Vec<Option<Vec<u8>>>),
+}
+
+impl Values {
+    /// Converts a column of a DataFrame: floats become DOUBLE, integers INT64, booleans BOOLEAN and
+    /// all other columns (e.g. ```validdate```) UTF-8 strings.
+    fn from_series(series: &Series) -> std::result::Result<Values, ConnectorError> {
+        let values = match series.dtype() {
+            DataType::Boolean => Values::Boolean(series.bool()?.into_iter().collect()),
+            DataType::Float32 | DataType::Float64 => {
+                Values::Double(series.cast(&DataType::Float64)?.f64()?.into_iter().collect())
+            }
+            dtype if dtype.is_numeric() => {
+                Values::Int64(series.cast(&DataType::Int64)?.i64()?.into_iter().collect())
+            }
+            _ => Values::String(
+                series.cast(&DataType::Utf8)?.utf8()?.into_iter().map(|v| v.map(String::from)).collect()
+            ),
+        };
+        Ok(values)
+    }
+
+    fn physical_type(&self) -> Type {
+        match self {
+            Values::Boolean(_) => Type::BOOLEAN,
+            Values::Int64(_) => Type::INT64,
+            Values::Double(_) => Type::DOUBLE,
+            Values::String(_) | Values::Binary(_) => Type::BYTE_ARRAY,
+        }
+    }
+
+    fn is_valid(&self) -> Vec<bool> {
+        match self {
+            Values::Boolean(v) => v.iter().map(Option::is_some).collect(),
+            Values::Int64(v) => v.iter().map(Option::is_some).collect(),
+            Values::Double(v) => v.iter().map(Option::is_some).collect(),
+            Values::String(v) => v.iter().map(Option::is_some).collect(),
+            Values::Binary(v) => v.iter().map(Option::is_some).collect(),
+        }
+    }
+
+    /// PLAIN encoding of the present values.
+    fn encode_plain(&self) -> Vec<u8> {
+        let mut buffer = Vec::new();
+        match self {
+            Values::Boolean(v) => {
+                let bits: Vec<bool> = v.iter().flatten().copied().collect();
+                buffer.extend(pack_bits(&bits));
+            }
+            Values::Int64(v) => v.iter().flatten().for_each(|x| buffer.extend_from_slice(&x.to_le_bytes())),
+            Values::Double(v) => v.iter().flatten().for_each(|x| buffer.extend_from_slice(&x.to_le_bytes())),
+            Values::String(v) => v.iter().flatten().for_each(|x| push_byte_array(&mut buffer, x.as_bytes())),
+            Values::Binary(v) => v.iter().flatten().for_each(|x| push_byte_array(&mut buffer, x)),
+        }
+        buffer
+    }
+}
+
+fn push_byte_array(buffer: &mut Vec<u8>, bytes: &[u8]) {
+    buffer.extend_from_slice(&(bytes.len() as u32).to_le_bytes());
+    buffer.extend_from_slice(bytes);
+}
+
+/// Packs booleans into bytes, least significant bit first (padded with zeros).
+fn pack_bits(bits: &[bool]) -> Vec<u8> {
+    bits.chunks(8)
+        .map(|chunk| chunk.iter().enumerate().fold(0u8, |byte, (i, bit)| byte | ((*bit as u8) << i)))
+        .collect()
+}
+
+/// Encodes the definition levels of an optional column (0 for missing, 1 for present values) as a
+/// single bit-packed run of the RLE/bit-packing hybrid, prefixed by its length as in a data page v1.
+fn encode_definition_levels(is_valid: &[bool]) -> Vec<u8> {
+    let mut run = Vec::new();
+    let groups = is_valid.len().div_ceil(8);
+    let mut header = ((groups as u64) << 1) | 1;
+    // ULEB128 run header
+    loop {
+        let byte = (header & 0x7f) as u8;
+        header >>= 7;
+        if header == 0 {
+            run.push(byte);
+            break;
+        }
+        run.push(byte | 0x80);
+    }
+    run.extend(pack_bits(is_valid));
+
+    let mut levels = (run.len() as u32).to_le_bytes().to_vec();
+    levels.extend(run);
+    levels
+}
+
+fn thrift_bytes(
+    write: impl FnOnce(&mut TCompactOutputProtocol<&mut Vec<u8>>) -> parquet_format_safe::thrift::Result<usize>,
+) -> std::result::Result<Vec<u8>, ConnectorError> {
+    let mut buffer = Vec::new();
+    let mut protocol = TCompactOutputProtocol::new(&mut buffer);
+    write(&mut protocol).map_err(|e| ConnectorError::LibraryError(format!("cannot encode Parquet metadata: {}", e)))?;
+    Ok(buffer)
+}
+
+/// Builds the GeoParquet metadata (the JSON stored under the "geo" key) for the points of the
+/// geometry column, including their bounding box.
+fn geo_metadata(lat: &[Option<f64>], lon: &[Option<f64>]) -> String {
+    let points: Vec<(f64, f64)> = lat.iter().zip(lon).filter_map(|(lat, lon)| Some(((*lon)?, (*lat)?))).collect();
+    let mut column = serde_json::json!({
+        "encoding": "WKB",
+        "geometry_types": ["Point"],
+    });
+    if !points.is_empty() {
+        let (min_x, max_x) = points.iter().fold((f64::MAX, f64::MIN), |(min, max), (x, _)| (min.min(*x), max.max(*x)));
+        let (min_y, max_y) = points.iter().fold((f64::MAX, f64::MIN), |(min, max), (_, y)| (min.min(*y), max.max(*y)));
+        column["bbox"] = serde_json::json!([min_x, min_y, max_x, max_y]);
+    }
+    serde_json::json!({
+        "version": "1.0.0",
+        "primary_column": GEOMETRY_COLUMN,
+        "columns": { GEOMETRY_COLUMN: column },
+    })
+    .to_string()
+}
+
+/// Encodes the DataFrame with an additional WKB ```geometry``` column as a GeoParquet file. The
+/// DataFrame needs ```lat``` and ```lon``` columns; rows without coordinates get a missing geometry.
+pub(crate) fn to_geoparquet_bytes(df: &DataFrame) -> std::result::Result<Vec<u8>, ConnectorError> {
+    let mut coordinates = Vec::with_capacity(2);
+    for key in ["lat", "lon"] {
+        match df.column(key) {
+            Ok(series) => coordinates.push(series.cast(&DataType::Float64)?.f64()?.into_iter().collect::<Vec<_>>()),
+            Err(_) => return Err(ConnectorError::LibraryError(format!("column '{}' is missing", key))),
+        }
+    }
+    if df.column(GEOMETRY_COLUMN).is_ok() {
+        return Err(ConnectorError::LibraryError(format!("column '{}' already exists", GEOMETRY_COLUMN)));
+    }
+    let (lat, lon) = (&coordinates[0], &coordinates[1]);
+    let geometry = lat
+        .iter()
+        .zip(lon)
+        .map(|(lat, lon)| Some(point_to_wkb((*lat)?, (*lon)?)))
+        .collect();
+
+    let mut columns = Vec::with_capacity(df.width() + 1);
+    for series in df.get_columns() {
+        columns.push((series.name().to_string(), Values::from_series(series)?));
+    }
+    columns.push((GEOMETRY_COLUMN.to_string(), Values::Binary(geometry)));
+
+    let num_rows = df.height() as i64;
+    let mut file = MAGIC.to_vec();
+    let mut schema = vec![SchemaElement::new(
+        None, None, None, String::from("schema"), columns.len() as i32, None, None, None, None, None
+    )];
+    let mut chunks = Vec::with_capacity(columns.len());
+    for (name, values) in &columns {
+        let (converted_type, logical_type) = match values {
+            Values::String(_) => (Some(ConvertedType::UTF8), Some(LogicalType::STRING(StringType::new()))),
+            _ => (None, None),
+        };
+        schema.push(SchemaElement::new(
+            values.physical_type(),
+            None,
+            FieldRepetitionType::OPTIONAL,
+            name.clone(),
+            None,
+            converted_type,
+            None,
+            None,
+            None,
+            logical_type,
+        ));
+
+        // One data page v1: definition levels followed by the present values.
+        let mut page = encode_definition_levels(&values.is_valid());
+        page.extend(values.encode_plain());
+        let data_page_header = DataPageHeader::new(num_rows as i32, Encoding::PLAIN, Encoding::RLE, Encoding::RLE, None);
+        let page_header = PageHeader::new(
+            PageType::DATA_PAGE, page.len() as i32, page.len() as i32, None, data_page_header, None, None, None
+        );
+        let page_header = thrift_bytes(|protocol| page_header.write_to_out_protocol(protocol))?;
+
+        let offset = file.len() as i64;
+        let size = (page_header.len() + page.len()) as i64;
+        file.extend(page_header);
+        file.extend(page);
+        let meta_data = ColumnMetaData::new(
+            values.physical_type(),
+            vec![Encoding::PLAIN, Encoding::RLE],
+            vec![name.clone()],
+            CompressionCodec::UNCOMPRESSED,
+            num_rows,
+            size,
+            size,
+            None,
+            offset,
+            None,
+            None,
+            None,
+            None,
+            None,
+        );
+        chunks.push(ColumnChunk::new(None, offset, meta_data, None, None, None, None, None, None));
+    }
+
+    let total_byte_size = file.len() as i64 - MAGIC.len() as i64;
+    let row_group = RowGroup::new(chunks, total_byte_size, num_rows, None, None, None, None);
+    let geo = KeyValue::new(String::from("geo"), geo_metadata(lat, lon));
+    let metadata = FileMetaData::new(
+        1, schema, num_rows, vec![row_group], vec![geo], String::from("meteomatics-rust-connector"), None, None, None
+    );
+    let metadata = thrift_bytes(|protocol| metadata.write_to_out_protocol(protocol))?;
+    file.extend_from_slice(&metadata);
+    file.extend_from_slice(&(metadata.len() as u32).to_le_bytes());
+    file.extend_from_slice(MAGIC);
+    Ok(file)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::geoparquet::point_to_wkb;
+    use parquet_format_safe::thrift::protocol::TCompactInputProtocol;
+    use parquet_format_safe::{ColumnChunk, FileMetaData, PageHeader, Type};
+    use polars::prelude::*;
+
+    /// Reads the footer of a Parquet file.
+    fn read_metadata(file: &[u8]) -> FileMetaData {
+        assert_eq!(&file[..4], b"PAR1");
+        assert_eq!(&file[file.len() - 4..], b"PAR1");
+        let len = u32::from_le_bytes(file[file.len() - 8..file.len() - 4].try_into().unwrap()) as usize;
+        let footer = &file[file.len() - 8 - len..file.len() - 8];
+        FileMetaData::read_from_in_protocol(&mut TCompactInputProtocol::new(footer, usize::MAX)).unwrap()
+    }
+
+    /// Reads the single data page of a column chunk and returns which values are present and the
+    /// PLAIN encoded present values.
+    fn read_page<'a>(file: &'a [u8], chunk: &ColumnChunk) -> (Vec<bool>, &'a [u8]) {
+        let mut page = &file[chunk.meta_data.as_ref().unwrap().data_page_offset as usize..];
+        let header = PageHeader::read_from_in_protocol(&mut TCompactInputProtocol::new(&mut page, usize::MAX)).unwrap();
+        let num_values = header.data_page_header.unwrap().num_values as usize;
+        let page = &page[..header.uncompressed_page_size as usize];
+
+        // A single bit-packed run with a one byte header.
+        let levels_len = u32::from_le_bytes(page[..4].try_into().unwrap()) as usize;
+        assert_eq!(page[4] & 1, 1);
+        let is_valid = (0..num_values).map(|i| page[5 + i / 8] & (1 << (i % 8)) != 0).collect();
+        (is_valid, &page[4 + levels_len..])
+    }
+
+    #[tokio::test]
+    async fn check_geoparquet_round_trip() {
+        let df = df!(
+            "lat" => &[Some(47.42), Some(46.95), None],
+            "lon" => &[Some(9.37), Some(7.43), Some(8.0)],
+            "validdate" => &["1989-11-09T18:00:00Z", "1989-11-09T18:00:00Z", "1989-11-09T18:00:00Z"],
+            "t_2m:C" => &[Some(11.5), None, Some(12.8)]
+        ).unwrap();
+        let dir = std::env::temp_dir().join(format!("meteomatics_geoparquet_{}", std::process::id()));
+        let file_name = dir.join("points.parquet").to_str().unwrap().to_string();
+        crate::util::df_to_geoparquet(&df, &file_name).await.unwrap();
+        let file = std::fs::read(&file_name).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        // The GeoParquet metadata describes the geometry column.
+        let metadata = read_metadata(&file);
+        assert_eq!(metadata.num_rows, 3);
+        let geo = metadata.key_value_metadata.as_ref().unwrap().iter().find(|kv| kv.key == "geo").unwrap();
+        let geo: serde_json::Value = serde_json::from_str(geo.value.as_ref().unwrap()).unwrap();
+        assert_eq!(geo["primary_column"], "geometry");
+        assert_eq!(geo["columns"]["geometry"]["encoding"], "WKB");
+        assert_eq!(geo["columns"]["geometry"]["geometry_types"], serde_json::json!(["Point"]));
+        assert_eq!(geo["columns"]["geometry"]["bbox"], serde_json::json!([7.43, 46.95, 9.37, 47.42]));
+
+        // The columns of the DataFrame followed by the geometry column.
+        let names: Vec<&str> = metadata.schema[1..].iter().map(|e| e.name.as_str()).collect();
+        assert_eq!(names, &["lat", "lon", "validdate", "t_2m:C", "geometry"]);
+        let columns = &metadata.row_groups[0].columns;
+        assert_eq!(columns[4].meta_data.as_ref().unwrap().type_, Type::BYTE_ARRAY);
+
+        // The values, with missing values as undefined.
+        let (is_valid, values) = read_page(&file, &columns[3]);
+        assert_eq!(is_valid, vec![true, false, true]);
+        let values: Vec<f64> = values.chunks(8).map(|v| f64::from_le_bytes(v.try_into().unwrap())).collect();
+        assert_eq!(values, vec![11.5, 12.8]);
+
+        let (is_valid, values) = read_page(&file, &columns[2]);
+        assert_eq!(is_valid, vec![true; 3]);
+        assert_eq!(&values[..4], &20u32.to_le_bytes());
+        assert_eq!(&values[4..24], b"1989-11-09T18:00:00Z");
+
+        // The WKB points, no geometry without a latitude.
+        let (is_valid, values) = read_page(&file, &columns[4]);
+        assert_eq!(is_valid, vec![true, true, false]);
+        assert_eq!(&values[..4], &21u32.to_le_bytes());
+        assert_eq!(values[4..25], point_to_wkb(47.42, 9.37));
+        assert_eq!(values[29..50], point_to_wkb(46.95, 7.43));
+        assert_eq!(values.len(), 50);
+
+        // The coordinates are required.
+        assert!(crate::util::df_to_geoparquet(&df.drop("lat").unwrap(), &file_name).await.is_err());
+    }
+
+    #[test]
+    fn check_point_to_wkb() {
+        let wkb = point_to_wkb(47.5, 9.25);
+        assert_eq!(wkb.len(), 21);
+        assert_eq!(&wkb[..5], &[1, 1, 0, 0, 0]);
+        assert_eq!(f64::from_le_bytes(wkb[5..13].try_into().unwrap()), 9.25);
+        assert_eq!(f64::from_le_bytes(wkb[13..21].try_into().unwrap()), 47.5);
+    }
+
+    #[test]
+    fn check_definition_levels() {
+        // 10 levels = 2 groups of 8: header (2 << 1) | 1, then the bits least significant first.
+        let levels = crate::geoparquet::encode_definition_levels(&[true, false, true, true, true, true, true, true, false, true]);
+        assert_eq!(levels, vec![3, 0, 0, 0, 5, 0b1111_1101, 0b0000_0010]);
+    }
+}
@@ -106,13 +106,27 @@
 //! └───────┴────────┴──────────────────────┴────────┘
 //! ```
 
+pub mod cache;
 pub mod errors;
 pub mod client;
+pub mod connector_components;
 pub mod location;
+pub mod query;
 pub mod util;
+pub mod validation;
+#[cfg(feature = "geoparquet")]
+mod geoparquet;
+
+#[cfg(test)]
+mod testing;
+
 pub use client::APIClient;
 pub use location::Point;
 pub use location::BBox;
 pub use util::TimeSeries;
 pub use chrono::{Duration, DateTime, Local, Utc};
-pub use polars::frame::DataFrame;
\ No newline at end of file
+pub use polars::frame::DataFrame;
+#[cfg(feature = "tz")]
+pub use chrono_tz;
+#[cfg(feature = "ndarray")]
+pub use ndarray;
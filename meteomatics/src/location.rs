@@ -25,10 +25,14 @@
 //! };
 //! ```
 
+use crate::errors::ConnectorError;
 use std::fmt;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
 
 /// Define a location using its latitude and longitude coordinates. This is used in the generation of 
 /// the query in ```query_time_series()```.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Point {
     pub lat: f64,
     pub lon: f64,
@@ -37,6 +41,7 @@ pub struct Point {
 /// Define an area of interest by specifying a bounding box with coordinates at the upper left (lat_max, 
 /// lon_min) and lower right locations (lat_min, lon_max). This is used in the generation of the query
 /// in ```query_grid()``` and ```query_grid_time_series()```. 
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct BBox {
     pub lat_min: f64,
     pub lat_max: f64,
@@ -46,27 +51,478 @@ pub struct BBox {
     pub lon_res: f64,
 }
 
+/// A location of a time series query (see ```query_time_series_locations()```): a point, a postal
+/// code (e.g. "postal_CH9000") or a station identifier (e.g. "wmo_066810" or "metar_LSZH").
+pub enum Location {
+    Coord(Point),
+    Postal(String),
+    Station(String),
+}
+
+/// A location of a route query (see ```route_query()```): either a point or a postal code (e.g.
+/// "postal_CH9000").
+pub enum RouteLocation {
+    Point(Point),
+    Postal(String),
+}
+
+/// A stop of a route query: the location together with the point in time of the visit. Keeping both
+/// in one struct guarantees that the dates and locations of a route stay paired.
+pub struct RouteStop {
+    pub when: chrono::DateTime<chrono::Utc>,
+    pub location: RouteLocation,
+}
+
+/// The number of decimals of the coordinates of a ```Point``` in the query URL (about 0.1 m).
+pub const POINT_PRECISION: usize = 6;
+
+impl Point {
+    /// Formats the point like its ```Display``` implementation, but rounds the coordinates to
+    /// ```ndigits``` decimals. Trailing zeros are dropped, so e.g. ```13.460000000000001``` becomes
+    /// "13.46" with 6 digits.
+    pub fn to_string_prec(&self, ndigits: usize) -> String {
+        format!("{},{}", format_coordinate(self.lat, ndigits), format_coordinate(self.lon, ndigits))
+    }
+}
+
+/// Formats a line of ```n``` equidistant points from ```start``` to ```end``` (both included) as
+/// expected by the API (e.g. "50,10_50,20:100"). This is used in the generation of the query in
+/// ```query_line()```.
+pub fn line_to_str(start: &Point, end: &Point, n: usize) -> String {
+    format!("{}_{}:{}", start, end, n)
+}
+
+/// Rounds a coordinate to ```ndigits``` decimals without trailing zeros.
+fn format_coordinate(value: f64, ndigits: usize) -> String {
+    let formatted = format!("{:.*}", ndigits, value);
+    let formatted = if formatted.contains('.') {
+        formatted.trim_end_matches('0').trim_end_matches('.')
+    } else {
+        &formatted
+    };
+    // Small negative values round to "-0".
+    if formatted == "-0" { String::from("0") } else { formatted.to_string() }
+}
+
 /// This Display Trait implements the correct way of combining latitude and longitude coordinates for
-/// a Point. According to the MeteoMatics API specifications. 
-// TODO: Think about the number of significant digits and rounding/imprecision issues.
+/// a Point. According to the MeteoMatics API specifications. The coordinates are rounded to
+/// [`POINT_PRECISION`] decimals, so that floating point noise does not change the query URL.
 impl fmt::Display for Point {
     fn fmt(&self, f:&mut fmt::Formatter) -> fmt::Result {
-        write!(f, "{},{}", &self.lat.to_string(), &self.lon.to_string())
+        write!(f, "{}", self.to_string_prec(POINT_PRECISION))
+    }
+}
+
+/// This Display Trait formats a location as a point, a postal code or a station identifier.
+impl fmt::Display for Location {
+    fn fmt(&self, f:&mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Location::Coord(point) => write!(f, "{}", point),
+            Location::Postal(pcode) => write!(f, "{}", pcode),
+            Location::Station(station) => write!(f, "{}", station),
+        }
     }
 }
 
-/// This Display Trait implements the correct way of combining the bounding box coordinates. 
+/// This Display Trait formats a route location as a point or as a postal code.
+impl fmt::Display for RouteLocation {
+    fn fmt(&self, f:&mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RouteLocation::Point(point) => write!(f, "{}", point),
+            RouteLocation::Postal(pcode) => write!(f, "{}", pcode),
+        }
+    }
+}
+
+/// This Display Trait implements the correct way of combining the bounding box coordinates. Like for
+/// a ```Point```, the corners and the resolution are rounded to [`POINT_PRECISION`] decimals.
 impl fmt::Display for BBox {
     fn fmt(&self, f:&mut fmt::Formatter) -> fmt::Result {
         write!(
             f, 
             "{},{}_{},{}:{},{}", 
-            &self.lat_max, 
-            &self.lon_min, 
-            &self.lat_min, 
-            &self.lon_max,
-            &self.lat_res,
-            &self.lon_res
+            format_coordinate(self.lat_max, POINT_PRECISION), 
+            format_coordinate(self.lon_min, POINT_PRECISION), 
+            format_coordinate(self.lat_min, POINT_PRECISION), 
+            format_coordinate(self.lon_max, POINT_PRECISION),
+            format_coordinate(self.lat_res, POINT_PRECISION),
+            format_coordinate(self.lon_res, POINT_PRECISION)
+        )
+    }
+}
+
+impl BBox {
+    /// Creates the bounding box that extends ```half_lat_deg``` and ```half_lon_deg``` degrees from
+    /// the center in each direction (e.g. the area around a city). Fails if the bounding box is not
+    /// valid (see [`crate::validation::check_bbox`]), e.g. if it extends beyond the poles.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use meteomatics::{BBox, Point};
+    ///
+    /// let berlin = Point { lat: 52.52, lon: 13.405 };
+    /// let bbox = BBox::from_center(&berlin, 0.5, 0.5, 0.05, 0.05).unwrap();
+    /// assert_eq!(bbox.lat_min, 52.02);
+    /// ```
+    pub fn from_center(
+        center: &Point,
+        half_lat_deg: f64,
+        half_lon_deg: f64,
+        lat_res: f64,
+        lon_res: f64,
+    ) -> Result<BBox, ConnectorError> {
+        let bbox = BBox {
+            lat_min: center.lat - half_lat_deg,
+            lat_max: center.lat + half_lat_deg,
+            lon_min: center.lon - half_lon_deg,
+            lon_max: center.lon + half_lon_deg,
+            lat_res,
+            lon_res,
+        };
+        let issues = crate::validation::check_bbox(&bbox);
+        if !issues.is_empty() {
+            let messages: Vec<String> = issues.iter().map(|issue| issue.to_string()).collect();
+            return Err(ConnectorError::LibraryError(messages.join(", ")));
+        }
+        Ok(bbox)
+    }
+
+    /// Returns whether the point lies within the bounding box (including the edges).
+    pub fn contains(&self, p: &Point) -> bool {
+        (self.lat_min..=self.lat_max).contains(&p.lat) && (self.lon_min..=self.lon_max).contains(&p.lon)
+    }
+
+    /// Returns whether the two bounding boxes overlap. Boxes that only share an edge or a corner
+    /// intersect as well.
+    pub fn intersects(&self, other: &BBox) -> bool {
+        self.lat_min <= other.lat_max
+            && other.lat_min <= self.lat_max
+            && self.lon_min <= other.lon_max
+            && other.lon_min <= self.lon_max
+    }
+
+    /// Returns the overlap of the two bounding boxes with the resolution of ```self```, or ```None```
+    /// if they do not intersect (see ```intersects()```).
+    pub fn intersection(&self, other: &BBox) -> Option<BBox> {
+        if !self.intersects(other) {
+            return None;
+        }
+        Some(BBox {
+            lat_min: self.lat_min.max(other.lat_min),
+            lat_max: self.lat_max.min(other.lat_max),
+            lon_min: self.lon_min.max(other.lon_min),
+            lon_max: self.lon_max.min(other.lon_max),
+            lat_res: self.lat_res,
+            lon_res: self.lon_res,
+        })
+    }
+
+    /// Returns the number of grid points ```(n_lat, n_lon)``` the API returns for this bounding box at
+    /// its resolution. An axis with a resolution of zero (e.g. the BBox of a lightning query) counts
+    /// as a single point.
+    pub fn dimensions(&self) -> (usize, usize) {
+        (
+            axis_points(self.lat_max - self.lat_min, self.lat_res),
+            axis_points(self.lon_max - self.lon_min, self.lon_res),
         )
     }
-}
\ No newline at end of file
+
+    /// Returns the total number of grid points (```n_lat * n_lon```) of the bounding box, e.g. to check
+    /// a request against the grid-point quota before querying.
+    pub fn cell_count(&self) -> usize {
+        let (n_lat, n_lon) = self.dimensions();
+        n_lat * n_lon
+    }
+
+    /// Splits the bounding box into tiles of at most ```(lat, lon)``` degrees. The tile size is
+    /// rounded down to a multiple of the resolution (but at least one step), so that the grid points
+    /// of the tiles are the grid points of the whole bounding box. Neighbouring tiles share their edge
+    /// row or column.
+    pub fn tiles(&self, tile: (f64, f64)) -> Vec<BBox> {
+        let lat_edges = axis_edges(self.lat_min, self.lat_max, self.lat_res, tile.0);
+        let lon_edges = axis_edges(self.lon_min, self.lon_max, self.lon_res, tile.1);
+        let mut tiles = Vec::new();
+        for lat in lat_edges.windows(2) {
+            for lon in lon_edges.windows(2) {
+                tiles.push(BBox {
+                    lat_min: lat[0],
+                    lat_max: lat[1],
+                    lon_min: lon[0],
+                    lon_max: lon[1],
+                    lat_res: self.lat_res,
+                    lon_res: self.lon_res,
+                });
+            }
+        }
+        tiles
+    }
+
+    /// Returns the bounds of the bounding box as a GeoJSON Feature with a Polygon geometry, e.g. to
+    /// check the coverage of a query in QGIS or Leaflet.
+    pub fn to_geojson(&self) -> String {
+        let ring = vec![
+            [self.lon_min, self.lat_min],
+            [self.lon_max, self.lat_min],
+            [self.lon_max, self.lat_max],
+            [self.lon_min, self.lat_max],
+            [self.lon_min, self.lat_min],
+        ];
+        serde_json::json!({
+            "type": "Feature",
+            "geometry": { "type": "Polygon", "coordinates": [ring] },
+            "properties": { "lat_res": self.lat_res, "lon_res": self.lon_res },
+        })
+        .to_string()
+    }
+
+    /// Returns all grid points of the bounding box (see ```dimensions()```) as a GeoJSON Feature with
+    /// a MultiPoint geometry. Fails for grids with more than [`MAX_GEOJSON_POINTS`] points.
+    pub fn grid_points_geojson(&self) -> Result<String, ConnectorError> {
+        let n_points = self.cell_count();
+        if n_points > MAX_GEOJSON_POINTS {
+            return Err(ConnectorError::LibraryError(format!(
+                "grid has {} points, at most {} can be exported to GeoJSON", n_points, MAX_GEOJSON_POINTS
+            )));
+        }
+        let (n_lat, n_lon) = self.dimensions();
+        let lats = axis_coords(self.lat_min, self.lat_res, n_lat);
+        let lons = axis_coords(self.lon_min, self.lon_res, n_lon);
+        let points: Vec<[f64; 2]> = lats
+            .iter()
+            .flat_map(|lat| lons.iter().map(move |lon| [*lon, *lat]))
+            .collect();
+        Ok(serde_json::json!({
+            "type": "Feature",
+            "geometry": { "type": "MultiPoint", "coordinates": points },
+            "properties": { "n_lat": n_lat, "n_lon": n_lon },
+        })
+        .to_string())
+    }
+}
+
+/// The maximum number of grid points of [`BBox::grid_points_geojson`].
+pub const MAX_GEOJSON_POINTS: usize = 100_000;
+
+/// Coordinates of the ```n``` grid points along one axis starting at ```min```.
+fn axis_coords(min: f64, res: f64, n: usize) -> Vec<f64> {
+    (0..n)
+        // Round away the floating point noise of the multiplication (e.g. 47.300000000000004).
+        .map(|i| ((min + i as f64 * res) * 1e9).round() / 1e9)
+        .collect()
+}
+
+/// Edges of the tiles along one axis from ```min``` to ```max```, in steps of the tile size rounded
+/// down to a multiple of the resolution. Without a (positive) resolution the axis is not split.
+fn axis_edges(min: f64, max: f64, res: f64, tile: f64) -> Vec<f64> {
+    if res <= 0.0 || max <= min {
+        return vec![min, max];
+    }
+    let steps_per_tile = ((tile / res).floor() as usize).max(1);
+    let steps = (((max - min) / res).round() as usize).max(1);
+    let mut edges: Vec<f64> = (0..steps)
+        .step_by(steps_per_tile)
+        // Round away the floating point noise of the multiplication (e.g. 47.300000000000004).
+        .map(|i| ((min + i as f64 * res) * 1e9).round() / 1e9)
+        .collect();
+    edges.push(max);
+    edges
+}
+
+/// Number of grid points along one axis of the given span at the given resolution.
+fn axis_points(span: f64, res: f64) -> usize {
+    if res <= 0.0 {
+        return 1;
+    }
+    (span.abs() / res).round() as usize + 1
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::location::BBox;
+
+    #[test]
+    fn check_point_precision() {
+        use crate::location::Point;
+        let point = Point { lat: 47.1 + 0.2, lon: 13.36 + 0.1 };
+        assert_eq!(point.lon.to_string(), "13.459999999999999");
+        assert_eq!(point.to_string(), "47.3,13.46");
+        assert_eq!(Point { lat: 47.423938, lon: 9.372858 }.to_string(), "47.423938,9.372858");
+        assert_eq!(Point { lat: 47.4239384, lon: -9.0 }.to_string(), "47.423938,-9");
+        assert_eq!(Point { lat: -0.0000001, lon: 0.0 }.to_string(), "0,0");
+        assert_eq!(Point { lat: 47.423938, lon: 9.372858 }.to_string_prec(2), "47.42,9.37");
+
+        let bbox = BBox {
+            lat_min: 45.8,
+            lat_max: 45.8 + 0.1 * 3.0,
+            lon_min: 5.9,
+            lon_max: 10.5,
+            lat_res: 0.1 * 3.0,
+            lon_res: 0.05,
+        };
+        assert_eq!(bbox.to_string(), "46.1,5.9_45.8,10.5:0.3,0.05");
+    }
+
+    #[test]
+    fn check_line_string() {
+        use crate::location::{line_to_str, Point};
+        let start = Point { lat: 50.0, lon: 10.0 };
+        let end = Point { lat: 50.0, lon: 20.0 };
+        assert_eq!(line_to_str(&start, &end, 100), "50,10_50,20:100");
+        let end = Point { lat: -33.868820, lon: 151.209296 };
+        assert_eq!(line_to_str(&start, &end, 2), "50,10_-33.86882,151.209296:2");
+    }
+
+    #[test]
+    fn check_bbox_from_center() {
+        let center = crate::Point { lat: 47.0, lon: 8.0 };
+        let bbox = crate::BBox::from_center(&center, 1.0, 1.0, 0.1, 0.1).unwrap();
+        assert_eq!((bbox.lat_min, bbox.lat_max), (46.0, 48.0));
+        assert_eq!((bbox.lon_min, bbox.lon_max), (7.0, 9.0));
+        assert_eq!((bbox.lat_res, bbox.lon_res), (0.1, 0.1));
+
+        // Beyond the poles or the antimeridian
+        let north = crate::Point { lat: 89.5, lon: 8.0 };
+        assert!(crate::BBox::from_center(&north, 1.0, 1.0, 0.1, 0.1).is_err());
+        let east = crate::Point { lat: 47.0, lon: 179.5 };
+        assert!(crate::BBox::from_center(&east, 1.0, 1.0, 0.1, 0.1).is_err());
+        // Negative span or resolution
+        assert!(crate::BBox::from_center(&center, -1.0, 1.0, 0.1, 0.1).is_err());
+        assert!(crate::BBox::from_center(&center, 1.0, 1.0, 0.0, 0.1).is_err());
+    }
+
+    #[test]
+    fn check_bbox_geometry() {
+        let ch = crate::BBox { lat_min: 45.8, lat_max: 47.8, lon_min: 5.9, lon_max: 10.5, lat_res: 0.1, lon_res: 0.1 };
+
+        // Containment including the edges
+        assert!(ch.contains(&crate::Point { lat: 47.0, lon: 8.0 }));
+        assert!(ch.contains(&crate::Point { lat: 45.8, lon: 5.9 }));
+        assert!(ch.contains(&crate::Point { lat: 47.8, lon: 10.5 }));
+        assert!(!ch.contains(&crate::Point { lat: 47.81, lon: 8.0 }));
+        assert!(!ch.contains(&crate::Point { lat: 47.0, lon: 5.89 }));
+
+        // Disjoint boxes
+        let at = crate::BBox { lat_min: 46.4, lat_max: 49.0, lon_min: 11.0, lon_max: 17.2, lat_res: 0.05, lon_res: 0.05 };
+        assert!(!ch.intersects(&at));
+        assert!(ch.intersection(&at).is_none());
+
+        // Partial overlap with the resolution of self
+        let tile = crate::BBox { lat_min: 47.0, lat_max: 48.5, lon_min: 9.0, lon_max: 12.0, lat_res: 0.5, lon_res: 0.5 };
+        assert!(ch.intersects(&tile));
+        assert!(tile.intersects(&ch));
+        let overlap = ch.intersection(&tile).unwrap();
+        assert_eq!((overlap.lat_min, overlap.lat_max), (47.0, 47.8));
+        assert_eq!((overlap.lon_min, overlap.lon_max), (9.0, 10.5));
+        assert_eq!((overlap.lat_res, overlap.lon_res), (0.1, 0.1));
+
+        // Boxes sharing an edge intersect in a line
+        let east = crate::BBox { lat_min: 45.8, lat_max: 47.8, lon_min: 10.5, lon_max: 12.0, lat_res: 0.1, lon_res: 0.1 };
+        let edge = ch.intersection(&east).unwrap();
+        assert_eq!((edge.lon_min, edge.lon_max), (10.5, 10.5));
+    }
+
+    #[test]
+    fn check_bbox_tiles() {
+        let bbox = BBox {
+            lat_min: 45.8,
+            lat_max: 47.8,
+            lon_min: 5.9,
+            lon_max: 10.5,
+            lat_res: 0.1,
+            lon_res: 0.2,
+        };
+        let tiles = bbox.tiles((1.0, 2.5));
+        // 1.0° = 10 lat steps, 2.5° is rounded down to 12 lon steps (2.4°)
+        assert_eq!(tiles.len(), 2 * 2);
+        assert_eq!((tiles[0].lat_min, tiles[0].lat_max), (45.8, 46.8));
+        assert_eq!((tiles[0].lon_min, tiles[0].lon_max), (5.9, 8.3));
+        assert_eq!((tiles[3].lat_min, tiles[3].lat_max), (46.8, 47.8));
+        assert_eq!((tiles[3].lon_min, tiles[3].lon_max), (8.3, 10.5));
+
+        // A tile larger than the bounding box
+        assert_eq!(bbox.tiles((10.0, 10.0)).len(), 1);
+    }
+
+    #[test]
+    fn check_bbox_dimensions() {
+        let bbox = BBox {
+            lat_min: 45.8,
+            lat_max: 47.8,
+            lon_min: 5.9,
+            lon_max: 10.5,
+            lat_res: 0.1,
+            lon_res: 0.2,
+        };
+        assert_eq!(bbox.dimensions(), (21, 24));
+        assert_eq!(bbox.cell_count(), 504);
+
+        let lightning = BBox {
+            lat_min: 45.8,
+            lat_max: 47.8,
+            lon_min: 5.9,
+            lon_max: 10.5,
+            lat_res: 0.0,
+            lon_res: 0.0,
+        };
+        assert_eq!(lightning.dimensions(), (1, 1));
+        assert_eq!(lightning.cell_count(), 1);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn check_serde_round_trip() {
+        use crate::location::Point;
+        let point = Point { lat: 47.423938, lon: 9.372858 };
+        let json = serde_json::to_string(&point).unwrap();
+        assert_eq!(json, r#"{"lat":47.423938,"lon":9.372858}"#);
+        let point_back: Point = serde_json::from_str(&json).unwrap();
+        assert_eq!(point_back.to_string(), point.to_string());
+
+        let bbox = BBox {
+            lat_min: 45.8,
+            lat_max: 47.8,
+            lon_min: 5.9,
+            lon_max: 10.5,
+            lat_res: 0.1,
+            lon_res: 0.2,
+        };
+        let bbox_back: BBox = serde_json::from_str(&serde_json::to_string(&bbox).unwrap()).unwrap();
+        assert_eq!(bbox_back.to_string(), bbox.to_string());
+    }
+
+    #[test]
+    fn check_bbox_geojson() {
+        let bbox = BBox {
+            lat_min: 47.0,
+            lat_max: 47.5,
+            lon_min: 9.0,
+            lon_max: 9.3,
+            lat_res: 0.1,
+            lon_res: 0.1,
+        };
+        let polygon: serde_json::Value = serde_json::from_str(&bbox.to_geojson()).unwrap();
+        assert_eq!(polygon["geometry"]["type"], "Polygon");
+        let ring = polygon["geometry"]["coordinates"][0].as_array().unwrap();
+        assert_eq!(ring.len(), 5);
+        assert_eq!(ring[0], ring[4]);
+        assert_eq!(ring[2], serde_json::json!([9.3, 47.5]));
+
+        let points: serde_json::Value = serde_json::from_str(&bbox.grid_points_geojson().unwrap()).unwrap();
+        assert_eq!(points["geometry"]["type"], "MultiPoint");
+        let coordinates = points["geometry"]["coordinates"].as_array().unwrap();
+        assert_eq!(coordinates.len(), bbox.cell_count());
+        assert_eq!(coordinates.len(), 24);
+        assert_eq!(coordinates[23], serde_json::json!([9.3, 47.5]));
+
+        let world = BBox {
+            lat_min: -90.0,
+            lat_max: 90.0,
+            lon_min: -180.0,
+            lon_max: 180.0,
+            lat_res: 0.1,
+            lon_res: 0.1,
+        };
+        assert!(world.grid_points_geojson().is_err());
+    }
+}
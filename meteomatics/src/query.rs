@@ -0,0 +1,184 @@
+//! # Query
+//! This module provides a builder for time series queries as an alternative to the positional
+//! arguments of ```APIClient::query_time_series()```. The builder collects the parts of the query,
+//! ```build()``` checks that the query is complete and ```execute()``` sends it.
+//! ```rust, no_run
+//! use chrono::{Duration, Utc, TimeZone};
+//! use meteomatics::{APIClient, Point, TimeSeries};
+//! use meteomatics::query::TimeSeriesQuery;
+//!
+//! #[tokio::main]
+//! async fn main() {
+//!     let client = APIClient::new("ferris_loves_rustaceans", "0123456789", 10);
+//!     let start_date = Utc.ymd(1989, 11, 9).and_hms_micro(18, 0, 0, 0);
+//!     let query = TimeSeriesQuery::builder()
+//!         .time_series(TimeSeries {
+//!             start: start_date,
+//!             end: start_date + Duration::days(1),
+//!             timedelta: Option::from(Duration::hours(12))
+//!         })
+//!         .parameters(vec![String::from("t_2m:C")])
+//!         .points(vec![Point { lat: 52.52, lon: 13.405 }])
+//!         .optional("model=mix")
+//!         .build()
+//!         .unwrap();
+//!     let df = query.execute(&client).await.unwrap();
+//! }
+//! ```
+
+use crate::client::APIClient;
+use crate::errors::ConnectorError;
+use crate::location::Point;
+use crate::util::TimeSeries;
+
+/// A complete time series query (see [`TimeSeriesQueryBuilder`]).
+pub struct TimeSeriesQuery {
+    pub time_series: TimeSeries,
+    pub parameters: Vec<String>,
+    pub points: Vec<Point>,
+    pub optionals: Option<Vec<String>>,
+}
+
+impl TimeSeriesQuery {
+    /// Starts a new query.
+    pub fn builder() -> TimeSeriesQueryBuilder {
+        TimeSeriesQueryBuilder::default()
+    }
+
+    /// Sends the query with the client (see ```APIClient::query_time_series()```).
+    pub async fn execute(&self, client: &APIClient) -> Result<polars::frame::DataFrame, ConnectorError> {
+        client
+            .query_time_series(&self.time_series, &self.parameters, &self.points, &self.optionals)
+            .await
+    }
+}
+
+/// Collects the parts of a [`TimeSeriesQuery`].
+#[derive(Default)]
+pub struct TimeSeriesQueryBuilder {
+    time_series: Option<TimeSeries>,
+    parameters: Vec<String>,
+    points: Vec<Point>,
+    optionals: Vec<String>,
+}
+
+impl TimeSeriesQueryBuilder {
+    /// Sets the temporal extent of the query.
+    pub fn time_series(mut self, time_series: TimeSeries) -> Self {
+        self.time_series = Some(time_series);
+        self
+    }
+
+    /// Sets the names of the parameters (e.g. "t_2m:C" or "wind_speed_10m:ms").
+    pub fn parameters(mut self, parameters: Vec<String>) -> Self {
+        self.parameters = parameters;
+        self
+    }
+
+    /// Sets the locations of the query.
+    pub fn points(mut self, points: Vec<Point>) -> Self {
+        self.points = points;
+        self
+    }
+
+    /// Adds an optional parameter (e.g. "model=mix" or an ```Interpolation```).
+    pub fn optional(mut self, optional: impl Into<String>) -> Self {
+        self.optionals.push(optional.into());
+        self
+    }
+
+    /// Finishes the query. Fails if the time series is missing or if there are no parameters or
+    /// locations.
+    pub fn build(self) -> Result<TimeSeriesQuery, ConnectorError> {
+        let time_series = self
+            .time_series
+            .ok_or_else(|| ConnectorError::LibraryError(String::from("the time series is missing")))?;
+        if self.parameters.is_empty() {
+            return Err(ConnectorError::LibraryError(String::from("at least one parameter is required")));
+        }
+        if self.points.is_empty() {
+            return Err(ConnectorError::LibraryError(String::from("at least one point is required")));
+        }
+        let optionals = if self.optionals.is_empty() { None } else { Some(self.optionals) };
+
+        Ok(TimeSeriesQuery {
+            time_series,
+            parameters: self.parameters,
+            points: self.points,
+            optionals,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::query::TimeSeriesQuery;
+    use crate::testing::cached_client;
+    use crate::util::{build_ts_query_specs_with_precision, build_url, points_to_str};
+    use crate::{Point, TimeSeries};
+    use chrono::{Duration, SecondsFormat, TimeZone, Utc};
+
+    fn time_series() -> TimeSeries {
+        let start_date = Utc.ymd(1989, 11, 9).and_hms(18, 0, 0);
+        TimeSeries {
+            start: start_date,
+            end: start_date + Duration::days(1),
+            timedelta: Option::from(Duration::hours(12)),
+        }
+    }
+
+    #[test]
+    fn check_build_validation() {
+        let missing_time = TimeSeriesQuery::builder()
+            .parameters(vec![String::from("t_2m:C")])
+            .points(vec![Point { lat: 52.52, lon: 13.405 }])
+            .build();
+        assert!(missing_time.is_err());
+
+        let missing_params = TimeSeriesQuery::builder()
+            .time_series(time_series())
+            .points(vec![Point { lat: 52.52, lon: 13.405 }])
+            .build();
+        assert!(missing_params.is_err());
+
+        let missing_points = TimeSeriesQuery::builder()
+            .time_series(time_series())
+            .parameters(vec![String::from("t_2m:C")])
+            .build();
+        assert!(missing_points.is_err());
+    }
+
+    #[tokio::test]
+    async fn check_execute_matches_positional_query() {
+        let params = vec![String::from("t_2m:C")];
+        let points = vec![Point { lat: 52.52, lon: 13.405 }, Point { lat: 47.42, lon: 9.37 }];
+        let optionals = Some(vec![String::from("model=mix")]);
+
+        // The start has sub-second digits, the client truncates them to whole seconds.
+        let mut series = time_series();
+        series.start = series.start + Duration::milliseconds(250);
+
+        // Serve the response of the positional query from the cache.
+        let specs = build_ts_query_specs_with_precision(
+            &series, &params, &points_to_str(&points).await, &optionals, crate::connector_components::Format::Csv,
+            SecondsFormat::Secs
+        ).await.unwrap();
+        let url = build_url(&specs).await.unwrap();
+        let (mut client, cache) = cached_client("query");
+        client.with_timestamp_precision(SecondsFormat::Secs);
+        cache
+            .store(&url, b"lat;lon;validdate;t_2m:C\n52.52;13.405;1989-11-09T18:00:00Z;6.8\n47.42;9.37;1989-11-09T18:00:00Z;4.1\n")
+            .unwrap();
+
+        let query = TimeSeriesQuery::builder()
+            .time_series(series)
+            .parameters(params)
+            .points(points)
+            .optional("model=mix")
+            .build()
+            .unwrap();
+        assert_eq!(query.optionals, optionals);
+        let df = query.execute(&client).await.unwrap();
+        assert_eq!(df.shape(), (2, 4));
+    }
+}
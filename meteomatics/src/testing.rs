@@ -0,0 +1,113 @@
+//! # Testing
+//! Helpers for the unit tests: a local HTTP server that answers with canned responses and a temporary
+//! disk cache that serves the responses instead of the API.
+
+use crate::cache::DiskCache;
+use crate::errors::ConnectorError;
+use crate::APIClient;
+use std::io::{Read, Write};
+use std::net::{SocketAddr, TcpListener};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use url::Url;
+
+/// The raw requests received by a server of [`serve_recording()`], in order.
+pub type Requests = Arc<Mutex<Vec<String>>>;
+
+/// Starts a local HTTP server that answers one connection per response, in order, and stops after the
+/// last response. The responses are complete HTTP messages; with more than one response they should
+/// contain "connection: close", so that the client opens a new connection for every request.
+pub fn serve(responses: &[&str]) -> SocketAddr {
+    serve_recording(responses).0
+}
+
+/// Same as [`serve()`], but also returns the requests the server received (e.g. to check the headers
+/// or to count the requests).
+pub fn serve_recording(responses: &[&str]) -> (SocketAddr, Requests) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let requests = Requests::default();
+    let recorded = Arc::clone(&requests);
+    let responses: Vec<String> = responses.iter().map(|r| r.to_string()).collect();
+    std::thread::spawn(move || {
+        for (response, stream) in responses.into_iter().zip(listener.incoming()) {
+            let mut stream = stream.unwrap();
+            let mut buffer = [0u8; 4096];
+            let n = stream.read(&mut buffer).unwrap();
+            recorded.lock().unwrap().push(String::from_utf8_lossy(&buffer[..n]).to_string());
+            stream.write_all(response.as_bytes()).unwrap();
+        }
+    });
+    (addr, requests)
+}
+
+/// Starts a local server that accepts one connection but never answers (e.g. to test timeouts).
+pub fn hang() -> SocketAddr {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    std::thread::spawn(move || {
+        let (stream, _) = listener.accept().unwrap();
+        std::thread::sleep(std::time::Duration::from_secs(5));
+        drop(stream);
+    });
+    addr
+}
+
+/// Starts a local server that accepts one connection, sends the beginning of a response (e.g. the
+/// headers and a part of the body) and then stalls (e.g. to test what happens during a download).
+pub fn stall(head: &str) -> SocketAddr {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let head = head.to_string();
+    std::thread::spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        let mut buffer = [0u8; 4096];
+        let _ = stream.read(&mut buffer).unwrap();
+        stream.write_all(head.as_bytes()).unwrap();
+        std::thread::sleep(std::time::Duration::from_secs(5));
+        drop(stream);
+    });
+    addr
+}
+
+/// A disk cache in a temporary directory. The directory is removed when the cache is dropped, also
+/// if the test fails.
+pub struct TempCache {
+    pub dir: PathBuf,
+    cache: DiskCache,
+}
+
+impl TempCache {
+    /// Stores the response body for the URL, such that the client of [`cached_client()`] reads it
+    /// instead of sending the request.
+    pub fn store(&self, url: &Url, body: &[u8]) -> Result<(), ConnectorError> {
+        self.cache.store(url, None, body)
+    }
+
+    /// Whether there is a cache entry for the URL.
+    pub fn contains(&self, url: &Url) -> bool {
+        self.cache.path(url).exists()
+    }
+
+    /// Same as [`store()`](TempCache::store), with the ```Content-Type``` the response was sent with.
+    pub fn store_with_content_type(&self, url: &Url, content_type: &str, body: &[u8]) -> Result<(), ConnectorError> {
+        self.cache.store(url, Some(content_type), body)
+    }
+}
+
+impl Drop for TempCache {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.dir);
+    }
+}
+
+/// Returns a client that reads its responses from a temporary disk cache instead of the API, together
+/// with the cache. The ```name``` makes the directory unique per test.
+pub fn cached_client(name: &str) -> (APIClient, TempCache) {
+    let dir = std::env::temp_dir().join(format!("meteomatics_{}_{}", name, std::process::id()));
+    let ttl = std::time::Duration::from_secs(3600);
+    let mut api_client = APIClient::new("test_user", "test_password", 10);
+    api_client.with_disk_cache(dir.to_str().unwrap(), ttl);
+    let cache = DiskCache::new(dir.to_str().unwrap(), ttl).with_scope(&api_client.cache_scope());
+    (api_client, TempCache { dir, cache })
+}
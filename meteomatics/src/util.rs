@@ -11,15 +11,20 @@ use std::fs::File;
 use reqwest::Response;
 use url::{ParseError, Url};
 use crate::errors::ConnectorError;
+use crate::connector_components::Format;
 use std::path::Path;
 use std::fs;
 use polars::prelude::*;
-use crate::location::Point;
+use crate::location::{Location, Point, RouteStop, POINT_PRECISION};
 use std::fmt;
 
 // Default API URL
 const BASE_URL: &str = "https://api.meteomatics.com";
 
+/// The maximum length of a query URL. Longer time series queries are split into several requests
+/// (see ```APIClient::query_time_series()```) to avoid a "414 URI Too Long" response.
+pub const MAX_URL_LENGTH: usize = 2000;
+
 /// Container for time series information. This allows functions to use less parameters. 
 /// 
 /// # Arguments
@@ -43,29 +48,136 @@ const BASE_URL: &str = "https://api.meteomatics.com";
 /// println!("Time series: {}", time_series);
 /// ```
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct TimeSeries{
     pub start: chrono::DateTime<chrono::Utc>,
     pub end: chrono::DateTime<chrono::Utc>,
+    #[cfg_attr(feature = "serde", serde(with = "timedelta_millis"))]
     pub timedelta: Option<chrono::Duration>
 }
 
+/// (De)serializes the timedelta of a [`TimeSeries`] as a number of milliseconds, because
+/// ```chrono::Duration``` does not implement the serde traits.
+#[cfg(feature = "serde")]
+mod timedelta_millis {
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    pub fn serialize<S: Serializer>(timedelta: &Option<chrono::Duration>, serializer: S) -> Result<S::Ok, S::Error> {
+        timedelta.map(|d| d.num_milliseconds()).serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<chrono::Duration>, D::Error> {
+        Ok(Option::<i64>::deserialize(deserializer)?.map(chrono::Duration::milliseconds))
+    }
+}
+
+impl TimeSeries {
+    /// Iterates over all points in time of the time series, from ```start``` to ```end``` (inclusive)
+    /// in steps of ```timedelta```. Without a (positive) timedelta only ```start``` is returned.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use meteomatics::TimeSeries;
+    /// use chrono::{Duration, Utc};
+    /// let dt_start = Utc::now();
+    /// let time_series = TimeSeries {
+    ///     start: dt_start,
+    ///     end: dt_start + Duration::days(1),
+    ///     timedelta: Option::from(Duration::hours(12))
+    /// };
+    ///
+    /// assert_eq!(time_series.iter().count(), 3);
+    /// ```
+    pub fn iter(&self) -> impl Iterator<Item = chrono::DateTime<chrono::Utc>> + '_ {
+        let mut dt_next = Some(self.start);
+        std::iter::from_fn(move || {
+            let dt_cur = dt_next.filter(|dt| *dt <= self.end)?;
+            dt_next = match self.timedelta {
+                Some(timedelta) if timedelta > chrono::Duration::zero() => Some(dt_cur + timedelta),
+                _ => None,
+            };
+            Some(dt_cur)
+        })
+    }
+}
+
+impl TimeSeries {
+    /// Formats the time series like its ```Display``` implementation, but renders the start and the end
+    /// with the given precision (see [`format_timestamp`]). Fails for a time series without a timedelta,
+    /// which cannot be expressed in a query.
+    pub fn to_string_with_precision(&self, precision: chrono::SecondsFormat) -> std::result::Result<String, ConnectorError> {
+        let timedelta = self.timedelta.ok_or_else(|| ConnectorError::LibraryError(
+            String::from("the time series has no timedelta")
+        ))?;
+        Ok(format!(
+            "{}--{}:{}",
+            format_timestamp(&self.start, precision),
+            format_timestamp(&self.end, precision),
+            timedelta
+        ))
+    }
+
+    /// Splits the time series into consecutive time series that each span less than ```chunk```
+    /// (but at least one point in time). Every point in time of the time series is contained in
+    /// exactly one chunk, i.e. the chunk boundaries neither duplicate nor skip a timestamp.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use meteomatics::TimeSeries;
+    /// use chrono::{Duration, Utc};
+    /// let dt_start = Utc::now();
+    /// let time_series = TimeSeries {
+    ///     start: dt_start,
+    ///     end: dt_start + Duration::days(2),
+    ///     timedelta: Option::from(Duration::hours(12))
+    /// };
+    ///
+    /// let chunks = time_series.chunks(Duration::days(1));
+    /// assert_eq!(chunks.len(), 3);
+    /// assert_eq!(chunks[1].start, dt_start + Duration::days(1));
+    /// ```
+    pub fn chunks(&self, chunk: chrono::Duration) -> Vec<TimeSeries> {
+        let mut chunks: Vec<TimeSeries> = Vec::new();
+        for dt in self.iter() {
+            match chunks.last_mut() {
+                Some(last) if dt < last.start + chunk => last.end = dt,
+                _ => chunks.push(TimeSeries { start: dt, end: dt, timedelta: self.timedelta }),
+            }
+        }
+        chunks
+    }
+}
+
 impl fmt::Display for TimeSeries {
     fn fmt(&self, f:&mut fmt::Formatter) -> fmt::Result {
-        write!(
-            f, 
-            "{}--{}:{}", 
-            &self.start.to_rfc3339(),
-            &self.end.to_rfc3339(),
-            &self.timedelta.unwrap()
-        )
+        let time_series = self.to_string_with_precision(chrono::SecondsFormat::AutoSi).map_err(|_| fmt::Error)?;
+        write!(f, "{}", time_series)
     }
 }
 
+/// Formats a timestamp according to RFC 3339 with a fixed precision for the fractional seconds (e.g.
+/// ```SecondsFormat::Secs``` renders whole seconds only). Sub-second digits beyond the precision are
+/// truncated. ```SecondsFormat::AutoSi``` gives the same result as ```to_rfc3339()```.
+///
+/// # Arguments
+///
+/// * `timestamp` - Date and time to format.
+/// * `precision` - The precision of the fractional seconds.
+///
+pub fn format_timestamp(
+    timestamp: &chrono::DateTime<chrono::Utc>,
+    precision: chrono::SecondsFormat,
+) -> String {
+    timestamp.to_rfc3339_opts(precision, false)
+}
+
 /// Top-level struct for the De-serialization of the query results for <https://api.meteomatics.com/user_stats_json>.
 /// This query gives an overview about the request activity of your account as well as information about
 /// the feature availability for your account (e.g. if you are allowed to download gridded data or not.)
 /// The response contains a message together with all the statistics for your account.
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct UStatsResponse{
     pub message: String,
     #[serde(rename(serialize = "user statistics", deserialize = "user statistics"))]
@@ -73,7 +185,7 @@ pub struct UStatsResponse{
 }
 
 /// This contains the actual information about the account statistics, quota and permissions.
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct UserStats{
     pub username: String,
     #[serde(rename(serialize = "requests total", deserialize = "requests total"))]
@@ -98,9 +210,67 @@ pub struct UserStats{
     pub contact: Vec<String>,
 }
 
+impl UserStats {
+    /// Parses the historic request option (e.g. "1900-01-01T00:00:00Z--2100-01-01T00:00:00Z") into
+    /// the first and the last point in time the account has access to.
+    pub fn hist_range(&self) -> std::result::Result<(chrono::DateTime<chrono::Utc>, chrono::DateTime<chrono::Utc>), ConnectorError> {
+        parse_hist_range(&self.hist)
+    }
+
+    /// Returns whether any of the request quotas (total, since midnight, since the full hour and in
+    /// the last 60 seconds) has used at least ```fraction``` of its effective limit (see
+    /// [`Limit::effective`]), e.g. ```is_near_limit(0.9)``` to warn at 90 %. Unlimited quotas are
+    /// never near their limit.
+    pub fn is_near_limit(&self, fraction: f64) -> bool {
+        [&self.total, &self.since_midnight, &self.since_0, &self.since_60s]
+            .into_iter()
+            .any(|limit| match limit.effective() {
+                Some(lim) => limit.used as f64 >= fraction * lim as f64,
+                None => false,
+            })
+    }
+}
+
+/// Parses a time range of the form "<start>--<end>" (RFC 3339, e.g. the historic request option of
+/// the user statistics) into the start and end date.
+///
+/// # Arguments
+///
+/// * `hist` - The time range (e.g. "1900-01-01T00:00:00Z--2100-01-01T00:00:00Z").
+///
+pub fn parse_hist_range(hist: &str) -> std::result::Result<(chrono::DateTime<chrono::Utc>, chrono::DateTime<chrono::Utc>), ConnectorError> {
+    let parse = |d: &str| {
+        chrono::DateTime::parse_from_rfc3339(d.trim())
+            .map(|d| d.with_timezone(&chrono::Utc))
+            .map_err(|_| ConnectorError::LibraryError(format!("invalid historic request option '{}'", hist)))
+    };
+    match hist.split_once("--") {
+        Some((start, end)) => Ok((parse(start)?, parse(end)?)),
+        None => Err(ConnectorError::LibraryError(format!("invalid historic request option '{}'", hist))),
+    }
+}
+
+/// Checks that a time series does not start before the historic window of the account (see
+/// ```UserStats::hist_range()```).
+///
+/// # Arguments
+///
+/// * `time_series` - The time series of the query.
+/// * `window` - The first and the last point in time the account has access to.
+///
+pub fn check_historic_window(
+    time_series: &TimeSeries,
+    window: &(chrono::DateTime<chrono::Utc>, chrono::DateTime<chrono::Utc>),
+) -> std::result::Result<(), ConnectorError> {
+    if time_series.start < window.0 {
+        return Err(ConnectorError::LibraryError(String::from("requested date before account's historic window")));
+    }
+    Ok(())
+}
+
 /// The Limit struct is used to de-serialize the limit attributes of the account (e.g. how many 
 /// requests in parallel are allowed etc.)
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct Limit{
     pub used: u32,
     #[serde(rename(serialize = "soft limit", deserialize = "soft limit"))]
@@ -109,13 +279,211 @@ pub struct Limit{
     pub hard_lim: u32
 }
 
+impl Limit {
+    /// Returns the stricter of the soft and the hard limit. A value of 0 means that the corresponding
+    /// limit is not set, if neither limit is set ```None``` is returned.
+    pub fn effective(&self) -> Option<u32> {
+        [self.soft_lim, self.hard_lim].into_iter().filter(|l| *l > 0).min()
+    }
+
+    /// Returns how many requests are left until the soft limit is reached (0 if it is exceeded), or
+    /// ```None``` if the soft limit is not set (0).
+    pub fn remaining_soft(&self) -> Option<u32> {
+        remaining(self.used, self.soft_lim)
+    }
+
+    /// Returns how many requests are left until the hard limit is reached (0 if it is exceeded), or
+    /// ```None``` if the hard limit is not set (0).
+    pub fn remaining_hard(&self) -> Option<u32> {
+        remaining(self.used, self.hard_lim)
+    }
+}
+
+/// Remaining requests of a limit, where a limit of 0 means that the limit is not set.
+fn remaining(used: u32, limit: u32) -> Option<u32> {
+    match limit {
+        0 => None,
+        limit => Some(limit.saturating_sub(used)),
+    }
+}
+
+/// Metadata of an HTTP response of the API (e.g. to log the diagnostic or cache headers of a query).
+/// The url is the final URL of the response (after redirects). Of the headers of a response read from
+/// the disk cache only ```Content-Type``` is kept.
+#[derive(Debug, Clone)]
+pub struct ResponseMeta {
+    pub status: reqwest::StatusCode,
+    pub headers: reqwest::header::HeaderMap,
+    pub url: Url,
+}
+
+impl ResponseMeta {
+    /// Copies the metadata of the response (without consuming its body).
+    pub fn from_response(response: &Response) -> Self {
+        ResponseMeta {
+            status: response.status(),
+            headers: response.headers().clone(),
+            url: response.url().clone(),
+        }
+    }
+}
+
+/// Container for a pivoted grid parsed directly from the CSV response (without polars). The values
+/// are stored row by row, i.e. ```values[i][j]``` is the value at ```lats[i]``` and ```lons[j]```.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GridMatrix {
+    pub lats: Vec<f64>,
+    pub lons: Vec<f64>,
+    pub values: Vec<Vec<f64>>,
+}
+
+/// Names of the time and coordinate columns of a lightning query result (see ```query_lightning()```).
+/// The API names these columns "stroke_time:sql", "stroke_lat:d" and "stroke_lon:d". By default they
+/// are renamed to "validdate", "lat" and "lon" to match the other query results; use
+/// ```LightningColumns::original()``` to keep the names of the API or choose your own names.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LightningColumns {
+    pub validdate: String,
+    pub lat: String,
+    pub lon: String,
+}
+
+impl Default for LightningColumns {
+    fn default() -> Self {
+        Self {
+            validdate: String::from("validdate"),
+            lat: String::from("lat"),
+            lon: String::from("lon"),
+        }
+    }
+}
+
+impl LightningColumns {
+    /// The column names as returned by the API (i.e. no renaming).
+    pub fn original() -> Self {
+        Self {
+            validdate: String::from("stroke_time:sql"),
+            lat: String::from("stroke_lat:d"),
+            lon: String::from("stroke_lon:d"),
+        }
+    }
+}
+
+/// Renames the time and coordinate columns of a lightning query result from the names of the API to
+/// the given names.
+///
+/// # Arguments
+///
+/// * `df` - DataFrame as derived from the HTTP response of the lightning query.
+/// * `names` - The new names of the columns.
+///
+pub fn rename_lightning_columns(
+    df: &mut DataFrame,
+    names: &LightningColumns,
+) -> std::result::Result<(), ConnectorError> {
+    let original = LightningColumns::original();
+    df.rename(&original.validdate, &names.validdate)?;
+    df.rename(&original.lat, &names.lat)?;
+    df.rename(&original.lon, &names.lon)?;
+    Ok(())
+}
+
+/// A single lightning stroke as returned by ```query_lightning_strokes()```.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LightningStroke {
+    pub time: chrono::DateTime<chrono::Utc>,
+    pub lat: f64,
+    pub lon: f64,
+}
+
+/// A single row of a time series CSV as yielded by ```query_time_series_stream()```. The values are in
+/// the order of the parameters; missing values (e.g. "-") are ```None```.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CsvRow {
+    pub lat: f64,
+    pub lon: f64,
+    pub validdate: chrono::DateTime<chrono::Utc>,
+    pub values: Vec<Option<f64>>,
+}
+
+/// The temporal extent of a binary grid query (see ```query_grid_bytes()```): either a single point
+/// in time or a time series.
+#[derive(Debug, Clone, Copy)]
+pub enum GridTime<'a> {
+    Timestamp(&'a chrono::DateTime<chrono::Utc>),
+    TimeSeries(&'a TimeSeries),
+}
+
 // Deserializes the response for the user_stats_json query.
 pub async fn extract_user_statistics(response: Response) -> std::result::Result<UStatsResponse, ConnectorError> {
     let json: UStatsResponse = response.json::<UStatsResponse>().await?;
     Ok(json)
 }
 
-/// Writes the HTTP response to a file. 
+/// Counts the ensemble members contained in the response to an ensemble probe query (a single point
+/// and timestamp requested with ```ens_select=all```). The API returns one value column per member
+/// (e.g. "t_2m:C-m0", "t_2m:C-m1", ...), so every column apart from the time and location columns
+/// corresponds to one member.
+///
+/// # Arguments
+///
+/// * `df` - DataFrame as derived from the HTTP response of the probe query.
+///
+pub async fn extract_ensemble_size(df: &DataFrame) -> u32 {
+    df.get_column_names()
+        .iter()
+        .filter(|name| !matches!(**name, "validdate" | "lat" | "lon"))
+        .count() as u32
+}
+
+/// Reshapes the response of an ensemble query into a tall layout with a ```member``` column. The API
+/// returns one value column per member (e.g. "t_2m:C-m0", "t_2m:C-m1", ...); these columns are stacked
+/// into a single column named after the parameter and the member suffix (e.g. "m0" or "q0.9") is
+/// stored in the ```member``` column. A value column named exactly like the parameter (a selection of
+/// a single member) is labelled with ```default_member```.
+///
+/// # Arguments
+///
+/// * `df` - DataFrame as derived from the HTTP response of the ensemble query.
+/// * `parameter` - Name of the requested parameter (e.g. "t_2m:C").
+/// * `default_member` - The member label for a value column without suffix.
+///
+pub fn df_ensemble_to_long(
+    df: &DataFrame,
+    parameter: &str,
+    default_member: &str,
+) -> std::result::Result<DataFrame, ConnectorError> {
+    let prefix = format!("{}-", parameter);
+    let id_cols: Vec<&str> = df
+        .get_column_names()
+        .into_iter()
+        .filter(|name| *name != parameter && !name.starts_with(&prefix))
+        .collect();
+
+    let mut df_long: Option<DataFrame> = None;
+    for name in df.get_column_names() {
+        let member = if name == parameter {
+            default_member
+        } else if let Some(suffix) = name.strip_prefix(&prefix) {
+            suffix
+        } else {
+            continue;
+        };
+        let mut df_member = df.select(&id_cols)?;
+        df_member.with_column(Series::new("member", vec![member; df.height()]))?;
+        let mut values = df.column(name)?.cast(&DataType::Float64)?;
+        values.rename(parameter);
+        df_member.with_column(values)?;
+        df_long = match df_long {
+            None => Some(df_member),
+            Some(df_long) => Some(df_long.vstack(&df_member)?),
+        };
+    }
+
+    df_long.ok_or_else(|| ConnectorError::LibraryError(format!("no ensemble members of '{}' found", parameter)))
+}
+
+/// Writes the HTTP response to a file.
 /// 
 /// # Arguments
 /// 
@@ -124,13 +492,86 @@ pub async fn extract_user_statistics(response: Response) -> std::result::Result<
 /// 
 pub async fn write_file(response: Response, file_name: &String) -> std::result::Result<(), ConnectorError> {
     let body = response.bytes().await?;
-    let mut content = std::io::Cursor::new(body);
+    write_bytes(&body, file_name).await
+}
 
-    let mut file = File::create(file_name)?;
-    std::io::copy(&mut content, &mut file)?;
+/// Writes raw bytes (e.g. the result of ```query_grid_bytes()```) to a file. The bytes are written
+/// to a ".tmp" sibling that is renamed to the file name once it is complete, so a failed write never
+/// leaves a partial file behind (e.g. a truncated NetCDF that cannot be opened later).
+///
+/// # Arguments
+///
+/// * `body` - The bytes to be written.
+/// * `file_name` - The name for the file to be written (complete with path).
+///
+pub async fn write_bytes(body: &[u8], file_name: &String) -> std::result::Result<(), ConnectorError> {
+    let tmp_name = format!("{}.tmp", file_name);
+    let write = || -> std::io::Result<()> {
+        let mut content = std::io::Cursor::new(body);
+        let mut file = File::create(&tmp_name)?;
+        std::io::copy(&mut content, &mut file)?;
+        file.sync_all()?;
+        fs::rename(&tmp_name, file_name)
+    };
+    if let Err(e) = write() {
+        // The temporary file may not exist if creating it failed.
+        let _ = fs::remove_file(&tmp_name);
+        return Err(e.into());
+    }
     Ok(())
 }
 
+/// Replaces the fill value of a NetCDF variable (e.g. as downloaded by ```query_netcdf()```) with
+/// ```f64::NAN```, so that numeric consumers do not mistake missing data for values. The values and
+/// the ```_FillValue``` attribute of the variable are read with a NetCDF library (see
+/// ```netcdf_fill_to_nan()``` with the ```netcdf``` feature). Without a fill value the values are
+/// returned unchanged.
+///
+/// # Arguments
+///
+/// * `values` - The values of the variable.
+/// * `fill_value` - The ```_FillValue``` attribute of the variable, if it has one.
+///
+pub fn fill_to_nan(values: &[f64], fill_value: Option<f64>) -> Vec<f64> {
+    match fill_value {
+        Some(fill_value) => values
+            .iter()
+            .map(|v| if *v == fill_value { f64::NAN } else { *v })
+            .collect(),
+        None => values.to_vec(),
+    }
+}
+
+/// Reads a variable of a NetCDF file (e.g. as downloaded by ```query_netcdf()```) as flat ```f64```
+/// values in the order of its dimensions, with its ```_FillValue``` replaced by ```f64::NAN``` (see
+/// ```fill_to_nan()```).
+///
+/// # Arguments
+///
+/// * `file_name` - The path of the NetCDF file.
+/// * `variable` - The name of the variable (e.g. "t_2m").
+///
+/// # Examples
+///
+/// ```rust, no_run
+/// let values = meteomatics::util::netcdf_fill_to_nan("data/t_2m.nc", "t_2m").unwrap();
+/// let valid = values.iter().filter(|v| !v.is_nan()).count();
+/// ```
+#[cfg(feature = "netcdf")]
+pub fn netcdf_fill_to_nan(file_name: &str, variable: &str) -> std::result::Result<Vec<f64>, ConnectorError> {
+    let read_error = |e: netcdf::Error| ConnectorError::LibraryError(format!("cannot read {}: {}", file_name, e));
+    let file = netcdf::open(file_name).map_err(read_error)?;
+    let var = file.variable(variable).ok_or_else(|| {
+        ConnectorError::LibraryError(format!("{} has no variable '{}'", file_name, variable))
+    })?;
+    let fill_value = match var.attribute_value("_FillValue").transpose().map_err(read_error)? {
+        Some(value) => Some(f64::try_from(value).map_err(read_error)?),
+        None => None,
+    };
+    let values = var.get_values::<f64, _>(..).map_err(read_error)?;
+    Ok(fill_to_nan(&values, fill_value))
+}
+
 /// Creates a path if it does not already exist.
 /// 
 /// # Arguments
@@ -153,13 +594,863 @@ pub async fn create_path(file_name: &String) -> std::result::Result<(), Connecto
 
 }
 
+/// Turns a parameter name into a file name by replacing the characters that are not allowed (or
+/// awkward) in file names with '_' (e.g. "t_2m:C" -> "t_2m_C").
+pub fn sanitize_file_name(parameter: &str) -> String {
+    parameter
+        .chars()
+        .map(|c| if matches!(c, ':' | '/' | '\\') { '_' } else { c })
+        .collect()
+}
+
+/// Writes a [`DataFrame`](polars::frame::DataFrame) (e.g. the result of ```query_time_series()```)
+/// to a CSV file. The columns are separated by ';' to match the formatting of the API.
+///
+/// # Arguments
+///
+/// * `df` - The DataFrame to be written.
+/// * `file_name` - The name for the file to be written (complete with path). Intermediate directories
+///   will be created.
+///
+pub async fn write_df_csv(df: &DataFrame, file_name: &str) -> std::result::Result<(), ConnectorError> {
+    create_path(&file_name.to_string()).await?;
+
+    let mut file = File::create(file_name)?;
+    // The writer needs mutable access, cloning a DataFrame only clones the column references.
+    let mut df_out = df.clone();
+    CsvWriter::new(&mut file)
+        .has_header(true)
+        .with_delimiter(b';')
+        .finish(&mut df_out)?;
+    Ok(())
+}
+
+/// Writes a [`DataFrame`](polars::frame::DataFrame) as NDJSON (newline-delimited JSON), i.e. one JSON
+/// object per row. The ```validdate``` column is normalized to an ISO8601 string, numeric columns are
+/// written as JSON numbers and missing values as ```null```.
+///
+/// # Arguments
+///
+/// * `df` - The DataFrame to be written.
+/// * `writer` - The destination for the rows (e.g. a file or a buffer).
+///
+pub async fn write_df_ndjson<W: std::io::Write>(
+    df: &DataFrame,
+    writer: &mut W,
+) -> std::result::Result<(), ConnectorError> {
+    use serde_json::{Map, Number, Value};
+
+    // Convert the columns to JSON values first, this way every column is only cast once.
+    let mut columns: Vec<(&str, Vec<Value>)> = Vec::with_capacity(df.width());
+    for series in df.get_columns() {
+        let values = match series.dtype() {
+            DataType::Utf8 if series.name() == "validdate" => series
+                .utf8()?
+                .into_iter()
+                .map(|v| match v {
+                    Some(v) => chrono::DateTime::parse_from_rfc3339(v)
+                        .map(|dt| Value::from(dt.with_timezone(&chrono::Utc).to_rfc3339()))
+                        .map_err(|_| ConnectorError::ParseError),
+                    None => Ok(Value::Null),
+                })
+                .collect::<std::result::Result<Vec<Value>, ConnectorError>>()?,
+            DataType::Utf8 => series
+                .utf8()?
+                .into_iter()
+                .map(|v| v.map_or(Value::Null, Value::from))
+                .collect(),
+            _ => series
+                .cast(&DataType::Float64)?
+                .f64()?
+                .into_iter()
+                .map(|v| v.and_then(Number::from_f64).map_or(Value::Null, Value::Number))
+                .collect(),
+        };
+        columns.push((series.name(), values));
+    }
+
+    for row in 0..df.height() {
+        let object = columns
+            .iter()
+            .map(|(name, values)| (name.to_string(), values[row].clone()))
+            .collect::<Map<String, Value>>();
+        writeln!(writer, "{}", Value::Object(object))?;
+    }
+    Ok(())
+}
+
+/// Writes a [`DataFrame`](polars::frame::DataFrame) with ```lat``` and ```lon``` columns (e.g. the
+/// result of ```query_time_series()```) to a GeoParquet file. The coordinates are additionally stored
+/// as WKB points (longitude/latitude, WGS 84) in a ```geometry``` column, which is described by the
+/// GeoParquet metadata of the file (including the bounding box of the points). Like
+/// [`write_bytes`], the file is only renamed to the file name once it is complete.
+///
+/// # Arguments
+///
+/// * `df` - The DataFrame to be written.
+/// * `file_name` - The name for the file to be written (complete with path). Intermediate directories
+///   will be created.
+///
+#[cfg(feature = "geoparquet")]
+pub async fn df_to_geoparquet(df: &DataFrame, file_name: &str) -> std::result::Result<(), ConnectorError> {
+    let body = crate::geoparquet::to_geoparquet_bytes(df)?;
+    let file_name = file_name.to_string();
+    create_path(&file_name).await?;
+    write_bytes(&body, &file_name).await
+}
+
+/// Enriches a query result with user supplied metadata (e.g. the name, id or elevation of a site) by
+/// performing a left join of the result onto the metadata. All rows of the result are kept, rows
+/// without matching metadata get ```null``` values in the metadata columns.
+///
+/// # Arguments
+///
+/// * `result` - DataFrame as returned by one of the query methods (e.g. ```query_time_series()```).
+/// * `metadata` - DataFrame with the metadata, keyed by the same columns as the result.
+/// * `on` - The names of the key columns (e.g. ["lat", "lon"]).
+///
+pub fn join_metadata(
+    result: &DataFrame,
+    metadata: &DataFrame,
+    on: &[&str],
+) -> std::result::Result<DataFrame, ConnectorError> {
+    // Check for the key columns first to get a clear error message.
+    for key in on {
+        if result.column(key).is_err() {
+            return Err(ConnectorError::LibraryError(format!("join key '{}' is missing in the result", key)));
+        }
+        if metadata.column(key).is_err() {
+            return Err(ConnectorError::LibraryError(format!("join key '{}' is missing in the metadata", key)));
+        }
+    }
+    let df = result.join(metadata, on, on, JoinType::Left, None)?;
+    Ok(df)
+}
+
+/// Completes a tidy time series such that there is one row per requested point in time and location
+/// (for each location all points in time), in this order. Rows missing in the response get missing
+/// values, rows that were not requested are dropped. The DataFrame needs ```lat```, ```lon``` and
+/// ```validdate``` columns (see ```query_time_series()```). The locations are matched on the
+/// coordinates rounded like in the query URL (see [`POINT_PRECISION`]) and the points in time on the
+/// parsed ```validdate```, independent of its formatting.
+///
+/// # Arguments
+///
+/// * `df` - DataFrame as returned by ```query_time_series()```.
+/// * `dates` - The requested points in time.
+/// * `coordinates` - The requested locations.
+///
+pub fn complete_time_series(
+    df: &DataFrame,
+    dates: &[chrono::DateTime<chrono::Utc>],
+    coordinates: &[Point],
+) -> std::result::Result<DataFrame, ConnectorError> {
+    for key in ["lat", "lon", "validdate"] {
+        if df.column(key).is_err() {
+            return Err(ConnectorError::LibraryError(format!("column '{}' is missing", key)));
+        }
+    }
+    let lat = df.column("lat")?.cast(&DataType::Float64)?;
+    let lon = df.column("lon")?.cast(&DataType::Float64)?;
+    let validdate = df.column("validdate")?.cast(&DataType::Utf8)?;
+
+    // The server echoes the coordinates of the URL, i.e. rounded to POINT_PRECISION decimals.
+    let scale = 10f64.powi(POINT_PRECISION as i32);
+    let coordinate_key = |value: f64| (value * scale).round() as i64;
+
+    // Index of the row of every (lat, lon, validdate) in the response.
+    let mut rows = std::collections::HashMap::new();
+    for (i, ((lat, lon), validdate)) in lat.f64()?.into_iter().zip(lon.f64()?).zip(validdate.utf8()?).enumerate() {
+        let validdate = validdate.and_then(|d| chrono::DateTime::parse_from_rfc3339(d).ok());
+        if let (Some(lat), Some(lon), Some(validdate)) = (lat, lon, validdate) {
+            let key = (coordinate_key(lat), coordinate_key(lon), validdate.with_timezone(&chrono::Utc));
+            rows.entry(key).or_insert(i);
+        }
+    }
+    let mut indices: Vec<Option<u32>> = Vec::new();
+    for point in coordinates {
+        for date in dates {
+            let key = (coordinate_key(point.lat), coordinate_key(point.lon), *date);
+            indices.push(rows.get(&key).map(|i| *i as u32));
+        }
+    }
+    let dates: Vec<String> = dates
+        .iter()
+        .map(|d| d.to_rfc3339_opts(chrono::SecondsFormat::AutoSi, true))
+        .collect();
+
+    // Missing rows get missing values, the keys are taken from the request.
+    let mut df_out = df.take(&indices.into_iter().collect::<UInt32Chunked>())?;
+    let lats: Vec<f64> = coordinates.iter().flat_map(|p| std::iter::repeat_n(p.lat, dates.len())).collect();
+    let lons: Vec<f64> = coordinates.iter().flat_map(|p| std::iter::repeat_n(p.lon, dates.len())).collect();
+    let validdates: Vec<&str> = coordinates.iter().flat_map(|_| dates.iter().map(|d| d.as_str())).collect();
+    df_out.with_column(Series::new("lat", lats))?;
+    df_out.with_column(Series::new("lon", lons))?;
+    df_out.with_column(Series::new("validdate", validdates))?;
+    Ok(df_out)
+}
+
+/// Reshapes a tidy station time series (e.g. the result of ```query_time_series_postal()```) into a
+/// wide layout with one row per ```validdate``` and one column per station. This is the station
+/// analog to the pivoted grid. The stations and dates keep the order of their first appearance,
+/// combinations without a value are ```null```.
+///
+/// # Arguments
+///
+/// * `df` - DataFrame with a ```station_id```, a ```validdate``` and a value column.
+/// * `value_col` - The name of the value column to spread over the stations (e.g. "t_2m:C").
+///
+pub fn to_wide_by_station(df: &DataFrame, value_col: &str) -> std::result::Result<DataFrame, ConnectorError> {
+    for name in ["station_id", "validdate", value_col] {
+        if df.column(name).is_err() {
+            return Err(ConnectorError::LibraryError(format!("column '{}' is missing", name)));
+        }
+    }
+    let stations = df.column("station_id")?.cast(&DataType::Utf8)?;
+    let dates = df.column("validdate")?.cast(&DataType::Utf8)?;
+    let values = df.column(value_col)?.cast(&DataType::Float64)?;
+
+    let mut station_names: Vec<&str> = Vec::new();
+    let mut date_names: Vec<&str> = Vec::new();
+    let mut cells: std::collections::HashMap<(&str, &str), Option<f64>> = std::collections::HashMap::new();
+    for ((station, date), value) in stations.utf8()?.into_iter()
+        .zip(dates.utf8()?)
+        .zip(values.f64()?)
+    {
+        let (station, date) = match (station, date) {
+            (Some(station), Some(date)) => (station, date),
+            _ => continue,
+        };
+        if !station_names.contains(&station) {
+            station_names.push(station);
+        }
+        if !date_names.contains(&date) {
+            date_names.push(date);
+        }
+        if cells.insert((station, date), value).is_some() {
+            return Err(ConnectorError::LibraryError(
+                format!("duplicate value for station '{}' at '{}'", station, date)
+            ));
+        }
+    }
+
+    let mut columns = vec![Series::new("validdate", &date_names)];
+    for station in &station_names {
+        let column: Vec<Option<f64>> = date_names
+            .iter()
+            .map(|date| cells.get(&(*station, *date)).copied().flatten())
+            .collect();
+        columns.push(Series::new(station, &column));
+    }
+    let df = DataFrame::new(columns)?;
+    Ok(df)
+}
+
+// Splits the rows of the DataFrame into groups with equal values in the group columns (in the order of
+// their first appearance). Returns the key columns (one row per group) and the row indices of every
+// group. Without group columns all rows form a single group.
+fn group_rows(
+    df: &DataFrame,
+    group_cols: &[&str],
+) -> std::result::Result<(Vec<Series>, Vec<Vec<usize>>), ConnectorError> {
+    if group_cols.is_empty() {
+        return Ok((Vec::new(), vec![(0..df.height()).collect()]));
+    }
+    let groups = df.groupby_stable(group_cols)?;
+    let rows = groups
+        .get_groups()
+        .iter()
+        .map(|group| match group {
+            polars::frame::groupby::GroupsIndicator::Idx((_, rows)) => {
+                rows.iter().map(|i| *i as usize).collect()
+            }
+            polars::frame::groupby::GroupsIndicator::Slice([first, len]) => {
+                (first as usize..(first + len) as usize).collect()
+            }
+        })
+        .collect();
+    Ok((groups.keys(), rows))
+}
+
+/// Computes the Pearson correlation between two columns for every group of rows (e.g. per location
+/// over time). The result contains one row per group with the group columns and a ```correlation```
+/// column. Rows with a missing value in either column are ignored. Groups with less than two
+/// remaining rows or without variation in either column get a ```null``` correlation.
+///
+/// # Arguments
+///
+/// * `df` - DataFrame as returned by one of the query methods (e.g. ```query_time_series()```).
+/// * `col_a` - The name of the first column (e.g. "t_2m:C").
+/// * `col_b` - The name of the second column (e.g. "relative_humidity_2m:p").
+/// * `group_cols` - The names of the columns identifying a group (e.g. ["lat", "lon"]). Without
+///   group columns the correlation over all rows is computed.
+///
+pub fn correlate(
+    df: &DataFrame,
+    col_a: &str,
+    col_b: &str,
+    group_cols: &[&str],
+) -> std::result::Result<DataFrame, ConnectorError> {
+    for name in [col_a, col_b].iter().chain(group_cols.iter()) {
+        if df.column(name).is_err() {
+            return Err(ConnectorError::LibraryError(format!("column '{}' is missing", name)));
+        }
+    }
+    let a = df.column(col_a)?.cast(&DataType::Float64)?;
+    let a = a.f64()?;
+    let b = df.column(col_b)?.cast(&DataType::Float64)?;
+    let b = b.f64()?;
+
+    let pearson = |rows: &mut dyn Iterator<Item = usize>| -> Option<f64> {
+        let pairs: Vec<(f64, f64)> = rows
+            .filter_map(|i| match (a.get(i), b.get(i)) {
+                (Some(x), Some(y)) => Some((x, y)),
+                _ => None,
+            })
+            .collect();
+        if pairs.len() < 2 {
+            return None;
+        }
+        let n = pairs.len() as f64;
+        let mean_a = pairs.iter().map(|(x, _)| x).sum::<f64>() / n;
+        let mean_b = pairs.iter().map(|(_, y)| y).sum::<f64>() / n;
+        let (mut cov, mut var_a, mut var_b) = (0.0, 0.0, 0.0);
+        for (x, y) in &pairs {
+            cov += (x - mean_a) * (y - mean_b);
+            var_a += (x - mean_a).powi(2);
+            var_b += (y - mean_b).powi(2);
+        }
+        if var_a == 0.0 || var_b == 0.0 {
+            return None;
+        }
+        Some(cov / (var_a * var_b).sqrt())
+    };
+
+    let (mut columns, groups) = group_rows(df, group_cols)?;
+    let correlations: Vec<Option<f64>> = groups
+        .into_iter()
+        .map(|rows| pearson(&mut rows.into_iter()))
+        .collect();
+    columns.push(Series::new("correlation", &correlations));
+    let df = DataFrame::new(columns)?;
+    Ok(df)
+}
+
+/// Converts the ```validdate``` column from the strings returned by the API (e.g.
+/// "1989-11-09T18:00:00Z", also with fractional seconds or an offset) into a polars ```Datetime```
+/// column (UTC, microsecond precision), e.g. for time arithmetic and filtering.
+///
+/// # Arguments
+///
+/// * `df` - DataFrame as returned by one of the query methods (e.g. ```query_time_series()```).
+///
+pub fn parse_validdate_column(mut df: DataFrame) -> std::result::Result<DataFrame, ConnectorError> {
+    if df.column("validdate").is_err() {
+        return Err(ConnectorError::LibraryError(String::from("column 'validdate' is missing")));
+    }
+    let dates = df.column("validdate")?.cast(&DataType::Utf8)?;
+    let micros = dates
+        .utf8()?
+        .into_iter()
+        .map(|d| match d {
+            Some(d) => chrono::DateTime::parse_from_rfc3339(d)
+                .map(|d| Some(d.timestamp() * 1_000_000 + d.timestamp_subsec_micros() as i64))
+                .map_err(|_| ConnectorError::LibraryError(format!("invalid validdate '{}'", d))),
+            None => Ok(None),
+        })
+        .collect::<std::result::Result<Vec<Option<i64>>, ConnectorError>>()?;
+    let validdate = Series::new("validdate", &micros)
+        .cast(&DataType::Datetime(TimeUnit::Microseconds, None))?;
+    df.with_column(validdate)?;
+    Ok(df)
+}
+
+/// Converts the ```validdate``` column from UTC (as returned by the API) into local time of the given
+/// timezone, formatted according to RFC 3339 with the offset of the timezone at that point in time
+/// (e.g. "1989-11-09T19:00:00+01:00" for "Europe/Zurich"). Requires the ```tz``` feature.
+///
+/// # Arguments
+///
+/// * `df` - DataFrame as returned by one of the query methods (e.g. ```query_time_series()```).
+/// * `tz` - The timezone for the output (e.g. ```chrono_tz::Europe::Zurich```).
+///
+#[cfg(feature = "tz")]
+pub fn validdate_to_tz(mut df: DataFrame, tz: chrono_tz::Tz) -> std::result::Result<DataFrame, ConnectorError> {
+    if df.column("validdate").is_err() {
+        return Err(ConnectorError::LibraryError(String::from("column 'validdate' is missing")));
+    }
+    let dates = df.column("validdate")?.cast(&DataType::Utf8)?;
+    let local = dates
+        .utf8()?
+        .into_iter()
+        .map(|d| match d {
+            Some(d) => chrono::DateTime::parse_from_rfc3339(d)
+                .map(|d| Some(d.with_timezone(&tz).to_rfc3339_opts(chrono::SecondsFormat::AutoSi, false)))
+                .map_err(|_| ConnectorError::LibraryError(format!("invalid validdate '{}'", d))),
+            None => Ok(None),
+        })
+        .collect::<std::result::Result<Vec<Option<String>>, ConnectorError>>()?;
+    df.with_column(Series::new("validdate", local))?;
+    Ok(df)
+}
+
+/// Pivots an unpivoted grid DataFrame (e.g. the result of ```query_grid_unpivoted_time_series()```)
+/// into one dense 3D array per parameter with the axes (time, lat, lon), e.g. for numerical code. The
+/// axes are the distinct sorted values of the ```validdate```, ```lat``` and ```lon``` columns. Grid
+/// points missing in the DataFrame are ```NaN```.
+///
+/// # Arguments
+///
+/// * `df` - DataFrame with the columns ```lat```, ```lon```, ```validdate``` and one column per parameter.
+/// * `params` - The names of the parameter columns (e.g. "t_2m:C").
+///
+#[cfg(feature = "ndarray")]
+pub fn grid_df_to_ndarray(
+    df: &DataFrame,
+    params: &[String],
+) -> std::result::Result<std::collections::HashMap<String, ndarray::Array3<f64>>, ConnectorError> {
+    for name in ["lat", "lon", "validdate"].iter().copied().chain(params.iter().map(|p| p.as_str())) {
+        if df.column(name).is_err() {
+            return Err(ConnectorError::LibraryError(format!("column '{}' is missing", name)));
+        }
+    }
+    let lat = df.column("lat")?.cast(&DataType::Float64)?;
+    let lon = df.column("lon")?.cast(&DataType::Float64)?;
+    let dates = df.column("validdate")?.cast(&DataType::Utf8)?;
+    let lat: Vec<f64> = lat.f64()?.into_iter().map(|v| v.ok_or(ConnectorError::ParseError)).collect::<std::result::Result<_, _>>()?;
+    let lon: Vec<f64> = lon.f64()?.into_iter().map(|v| v.ok_or(ConnectorError::ParseError)).collect::<std::result::Result<_, _>>()?;
+    let dates: Vec<&str> = dates.utf8()?.into_iter().map(|v| v.ok_or(ConnectorError::ParseError)).collect::<std::result::Result<_, _>>()?;
+
+    // The axes: distinct sorted values
+    let axis = |values: &[f64]| {
+        let mut axis = values.to_vec();
+        axis.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+        axis.dedup();
+        axis
+    };
+    let lats = axis(&lat);
+    let lons = axis(&lon);
+    let mut validdates: Vec<String> = dates.iter().map(|d| d.to_string()).collect();
+    validdates.sort();
+    validdates.dedup();
+
+    // Position of every row in the arrays
+    let position = |axis: &[f64], v: f64| axis.iter().position(|a| *a == v).unwrap_or_default();
+    let index: Vec<(usize, usize, usize)> = (0..df.height())
+        .map(|row| {
+            let t = validdates.binary_search_by(|d| d.as_str().cmp(dates[row])).unwrap_or_default();
+            (t, position(&lats, lat[row]), position(&lons, lon[row]))
+        })
+        .collect();
+
+    let mut arrays = std::collections::HashMap::new();
+    for param in params {
+        let values = df.column(param)?.cast(&DataType::Float64)?;
+        let mut array = ndarray::Array3::from_elem((validdates.len(), lats.len(), lons.len()), f64::NAN);
+        for (i, value) in index.iter().zip(values.f64()?) {
+            array[*i] = value.unwrap_or(f64::NAN);
+        }
+        arrays.insert(param.clone(), array);
+    }
+    Ok(arrays)
+}
+
+/// Casts the given columns to ```Float64```. Columns that were parsed as strings because of
+/// non-numeric sentinels for missing values (e.g. "-" or "NaN") are parsed value by value, with
+/// every value that is not a finite number becoming ```null```.
+///
+/// # Arguments
+///
+/// * `df` - DataFrame as derived from the HTTP response.
+/// * `columns` - The names of the numeric columns (e.g. ["lat", "lon", "t_2m:C"]).
+///
+pub fn enforce_float_columns(
+    mut df: DataFrame,
+    columns: &[&str],
+) -> std::result::Result<DataFrame, ConnectorError> {
+    for name in columns {
+        let column = match df.column(name) {
+            Ok(column) => column,
+            Err(_) => return Err(ConnectorError::LibraryError(format!("column '{}' is missing", name))),
+        };
+        let values = match column.dtype() {
+            DataType::Float64 => continue,
+            DataType::Utf8 => {
+                let values: Vec<Option<f64>> = column
+                    .utf8()?
+                    .into_iter()
+                    .map(|v| v.and_then(|v| v.trim().parse::<f64>().ok()).filter(|v| v.is_finite()))
+                    .collect();
+                Series::new(name, &values)
+            }
+            _ => column.cast(&DataType::Float64)?,
+        };
+        df.with_column(values)?;
+    }
+    Ok(df)
+}
+
+/// Combines the unpivoted grids of neighbouring tiles (see ```query_grid_unpivoted_tiled()```) into
+/// one DataFrame. Rows of the shared tile edges are kept once and the result is sorted by ```lat```,
+/// ```lon``` and ```validdate```.
+///
+/// # Arguments
+///
+/// * `dfs` - The DataFrames of the tiles (with the same columns).
+///
+pub fn merge_grid_tiles(dfs: Vec<DataFrame>) -> std::result::Result<DataFrame, ConnectorError> {
+    let mut dfs = dfs.into_iter();
+    let mut merged = match dfs.next() {
+        Some(df) => df,
+        None => return Err(ConnectorError::LibraryError(String::from("no tiles to merge"))),
+    };
+    for df in dfs {
+        merged.vstack_mut(&df)?;
+    }
+    let keys = ["lat", "lon", "validdate"];
+    let subset: Vec<String> = keys
+        .iter()
+        .filter(|k| merged.column(k).is_ok())
+        .map(|k| k.to_string())
+        .collect();
+    let merged = merged.unique_stable(Some(&subset), UniqueKeepStrategy::First)?;
+    let reverse = vec![false; subset.len()];
+    let merged = merged.sort(subset, reverse)?;
+    Ok(merged)
+}
+
+/// Computes the diurnal cycle (hour-of-day climatology) of a value for every group of rows (e.g. per
+/// location over a long time series). The hour is taken from the parsed ```validdate``` (in UTC) and
+/// the result contains the group columns, the ```hour``` (0-23) and the ```mean``` and the sample
+/// standard deviation ```std``` of the values at that hour. Missing values are ignored; with less
+/// than two values the standard deviation is ```null```.
+///
+/// # Arguments
+///
+/// * `df` - DataFrame as returned by one of the query methods (e.g. ```query_time_series()```).
+/// * `value_col` - The name of the value column (e.g. "t_2m:C").
+/// * `group_cols` - The names of the columns identifying a group (e.g. ["lat", "lon"]).
+///
+pub fn diurnal_cycle(
+    df: &DataFrame,
+    value_col: &str,
+    group_cols: &[&str],
+) -> std::result::Result<DataFrame, ConnectorError> {
+    use chrono::Timelike;
+
+    for name in ["validdate", value_col].iter().chain(group_cols.iter()) {
+        if df.column(name).is_err() {
+            return Err(ConnectorError::LibraryError(format!("column '{}' is missing", name)));
+        }
+    }
+    let dates = df.column("validdate")?.cast(&DataType::Utf8)?;
+    let hours = dates
+        .utf8()?
+        .into_iter()
+        .map(|d| {
+            d.and_then(|d| chrono::DateTime::parse_from_rfc3339(d).ok())
+                .map(|d| d.with_timezone(&chrono::Utc).hour() as usize)
+                .ok_or(ConnectorError::ParseError)
+        })
+        .collect::<std::result::Result<Vec<usize>, ConnectorError>>()?;
+    let values = df.column(value_col)?.cast(&DataType::Float64)?;
+    let values = values.f64()?;
+
+    let (keys, groups) = group_rows(df, group_cols)?;
+    let mut key_idx: Vec<u32> = Vec::new();
+    let mut out_hours: Vec<u32> = Vec::new();
+    let mut means: Vec<f64> = Vec::new();
+    let mut stds: Vec<Option<f64>> = Vec::new();
+    for (g, rows) in groups.into_iter().enumerate() {
+        let mut by_hour: Vec<Vec<f64>> = vec![Vec::new(); 24];
+        for i in rows {
+            if let Some(value) = values.get(i) {
+                by_hour[hours[i]].push(value);
+            }
+        }
+        for (hour, hour_values) in by_hour.iter().enumerate().filter(|(_, v)| !v.is_empty()) {
+            let n = hour_values.len() as f64;
+            let mean = hour_values.iter().sum::<f64>() / n;
+            let std = if hour_values.len() < 2 {
+                None
+            } else {
+                Some((hour_values.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / (n - 1.0)).sqrt())
+            };
+            key_idx.push(g as u32);
+            out_hours.push(hour as u32);
+            means.push(mean);
+            stds.push(std);
+        }
+    }
+
+    let key_idx = UInt32Chunked::new("idx", &key_idx);
+    let mut columns = keys
+        .iter()
+        .map(|key| key.take(&key_idx))
+        .collect::<std::result::Result<Vec<Series>, PolarsError>>()?;
+    columns.push(Series::new("hour", &out_hours));
+    columns.push(Series::new("mean", &means));
+    columns.push(Series::new("std", &stds));
+    let df = DataFrame::new(columns)?;
+    Ok(df)
+}
+
+/// Detects the points in time where a value crosses a threshold (e.g. the temperature dropping below
+/// 0°C). The rows of every location (```lat```/```lon``` or ```station_id```, whichever is present)
+/// are scanned in their order and a crossing is reported at the first ```validdate``` on the other side
+/// of the threshold. A value equal to the threshold counts as above; missing values are skipped.
+/// The result contains the location columns, the ```validdate``` and the ```direction``` ("up" or
+/// "down") of every crossing.
+///
+/// # Arguments
+///
+/// * `df` - DataFrame as returned by one of the query methods (e.g. ```query_time_series()```).
+/// * `value_col` - The name of the value column (e.g. "t_2m:C").
+/// * `threshold` - The threshold in the unit of the value column.
+///
+pub fn threshold_crossings(
+    df: &DataFrame,
+    value_col: &str,
+    threshold: f64,
+) -> std::result::Result<DataFrame, ConnectorError> {
+    for name in ["validdate", value_col] {
+        if df.column(name).is_err() {
+            return Err(ConnectorError::LibraryError(format!("column '{}' is missing", name)));
+        }
+    }
+    let location_cols: Vec<&str> = df
+        .get_column_names()
+        .into_iter()
+        .filter(|name| matches!(*name, "lat" | "lon" | "station_id"))
+        .collect();
+    let values = df.column(value_col)?.cast(&DataType::Float64)?;
+    let values = values.f64()?;
+
+    // Collects the row index and direction of every crossing within the given rows.
+    let mut rows: Vec<u32> = Vec::new();
+    let mut directions: Vec<&str> = Vec::new();
+    let mut scan = |group: &mut dyn Iterator<Item = usize>| {
+        let mut above_prev: Option<bool> = None;
+        for i in group {
+            let above = match values.get(i) {
+                Some(value) => value >= threshold,
+                None => continue,
+            };
+            if let Some(above_prev) = above_prev {
+                if above != above_prev {
+                    rows.push(i as u32);
+                    directions.push(if above { "up" } else { "down" });
+                }
+            }
+            above_prev = Some(above);
+        }
+    };
+
+    let (_, groups) = group_rows(df, &location_cols)?;
+    for rows in groups {
+        scan(&mut rows.into_iter());
+    }
+
+    let mut out_cols = location_cols.clone();
+    out_cols.push("validdate");
+    let mut df_out = df.select(&out_cols)?.take(&UInt32Chunked::new("idx", &rows))?;
+    df_out.with_column(Series::new("direction", &directions))?;
+    Ok(df_out)
+}
+
+/// Splices an analysis and a forecast result into a single timeline (e.g. for dashboards). Rows of
+/// the analysis up to and including ```boundary``` and rows of the forecast after ```boundary``` are
+/// kept; a ```phase``` column marks them as "analysis" or "forecast". The forecast columns are cast
+/// to the types of the analysis columns, the analysis rows come first.
+///
+/// # Arguments
+///
+/// * `analysis` - The result for the past (e.g. from an analysis model).
+/// * `forecast` - The result for the future (e.g. from a forecast model), with the same columns.
+/// * `boundary` - The point in time where the analysis ends and the forecast starts.
+///
+pub fn merge_seamless(
+    analysis: &DataFrame,
+    forecast: &DataFrame,
+    boundary: &chrono::DateTime<chrono::Utc>,
+) -> std::result::Result<DataFrame, ConnectorError> {
+    // Selects the rows before (incl.) or after the boundary and labels them with the phase.
+    let select = |df: &DataFrame, before: bool, phase: &str| -> std::result::Result<DataFrame, ConnectorError> {
+        let dates = df.column("validdate")
+            .map_err(|_| ConnectorError::LibraryError(String::from("column 'validdate' is missing")))?
+            .cast(&DataType::Utf8)?;
+        let mut mask = Vec::with_capacity(df.height());
+        for date in dates.utf8()? {
+            let date = date
+                .and_then(|d| chrono::DateTime::parse_from_rfc3339(d).ok())
+                .ok_or(ConnectorError::ParseError)?;
+            mask.push((date <= *boundary) == before);
+        }
+        let mut df = df.filter(&BooleanChunked::new("mask", &mask))?;
+        let n = df.height();
+        df.with_column(Series::new("phase", vec![phase; n]))?;
+        Ok(df)
+    };
+
+    let df_analysis = select(analysis, true, "analysis")?;
+    let df_forecast = select(forecast, false, "forecast")?;
+    let columns = df_analysis
+        .get_columns()
+        .iter()
+        .map(|c| Ok(df_forecast.column(c.name())?.cast(c.dtype())?))
+        .collect::<std::result::Result<Vec<Series>, ConnectorError>>()?;
+    let df = df_analysis.vstack(&DataFrame::new(columns)?)?;
+    Ok(df)
+}
+
+/// Computes the simplified Wet Bulb Globe Temperature (WBGT) as used by the Australian Bureau of
+/// Meteorology: ```WBGT = 0.567 * T + 0.393 * e + 3.94``` with the water vapour pressure
+/// ```e = RH / 100 * 6.105 * exp(17.27 * T / (237.7 + T))``` in hPa. The formula only uses the air
+/// temperature and the relative humidity. It assumes moderately high radiation and light wind, so the
+/// index is an approximation and not a replacement for a measured WBGT.
+///
+/// # Arguments
+///
+/// * `t` - Air temperature in °C.
+/// * `rh` - Relative humidity in %.
+///
+pub fn simplified_wbgt(t: f64, rh: f64) -> f64 {
+    let e = rh / 100.0 * 6.105 * (17.27 * t / (237.7 + t)).exp();
+    0.567 * t + 0.393 * e + 3.94
+}
+
+/// Creates a new DataFrame with an added ```wbgt:C``` column computed from a temperature and a relative
+/// humidity column using [`simplified_wbgt`]. Missing inputs result in a missing index.
+///
+/// # Arguments
+///
+/// * `df_in` - DataFrame containing the temperature (°C) and the relative humidity (%).
+/// * `t_col` - The name of the temperature column (e.g. "t_2m:C").
+/// * `rh_col` - The name of the relative humidity column (e.g. "relative_humidity_2m:p").
+///
+pub fn df_add_wbgt(
+    df_in: DataFrame,
+    t_col: &str,
+    rh_col: &str,
+) -> std::result::Result<DataFrame, ConnectorError> {
+    let t = df_in.column(t_col)?.cast(&DataType::Float64)?;
+    let rh = df_in.column(rh_col)?.cast(&DataType::Float64)?;
+    let wbgt: Float64Chunked = t
+        .f64()?
+        .into_iter()
+        .zip(rh.f64()?)
+        .map(|(t, rh)| match (t, rh) {
+            (Some(t), Some(rh)) => Some(simplified_wbgt(t, rh)),
+            _ => None,
+        })
+        .collect();
+
+    let mut df_out = df_in;
+    df_out.with_column(wbgt.into_series().rename("wbgt:C").clone())?;
+    Ok(df_out)
+}
+
+/// Maps a source indicator to a confidence between 0 and 1. The indicator is the position of the
+/// source that delivered a value in a fallback chain or blend: 0 is the primary source (confidence
+/// 1.0), every further fallback level reduces the confidence by 0.25 down to a minimum of 0.25.
+/// Negative or non-integer indicators are not valid positions and result in ```None```.
+///
+/// # Arguments
+///
+/// * `indicator` - The position of the source in the fallback chain (0 = primary source).
+///
+pub fn source_confidence(indicator: f64) -> Option<f64> {
+    if indicator < 0.0 || indicator.fract() != 0.0 {
+        return None;
+    }
+    Some((1.0 - 0.25 * indicator).max(0.25))
+}
+
+/// Creates a new DataFrame with an added ```confidence``` column computed from a source indicator
+/// column using [`source_confidence`]. Missing or invalid indicators result in a missing confidence.
+///
+/// # Arguments
+///
+/// * `df_in` - DataFrame containing the source indicator.
+/// * `indicator_col` - The name of the source indicator column.
+///
+pub fn df_add_confidence(
+    df_in: DataFrame,
+    indicator_col: &str,
+) -> std::result::Result<DataFrame, ConnectorError> {
+    let indicator = df_in.column(indicator_col)?.cast(&DataType::Float64)?;
+    let confidence: Float64Chunked = indicator
+        .f64()?
+        .into_iter()
+        .map(|i| i.and_then(source_confidence))
+        .collect();
+
+    let mut df_out = df_in;
+    df_out.with_column(confidence.into_series().rename("confidence").clone())?;
+    Ok(df_out)
+}
+
+/// The mean radius of the earth in km, as used by [`haversine_km`].
+pub const EARTH_RADIUS_KM: f64 = 6371.0;
+
+/// Computes the great-circle distance in km between two points with the haversine formula on a
+/// sphere with radius [`EARTH_RADIUS_KM`]. The error compared to the ellipsoid is below 0.5 %.
+///
+/// # Arguments
+///
+/// * `a` - The first point.
+/// * `b` - The second point.
+///
+pub fn haversine_km(a: &Point, b: &Point) -> f64 {
+    let (lat_a, lat_b) = (a.lat.to_radians(), b.lat.to_radians());
+    let d_lat = lat_b - lat_a;
+    let d_lon = (b.lon - a.lon).to_radians();
+    let h = (d_lat / 2.0).sin().powi(2) + lat_a.cos() * lat_b.cos() * (d_lon / 2.0).sin().powi(2);
+    2.0 * EARTH_RADIUS_KM * h.sqrt().asin()
+}
+
+/// Creates a new DataFrame with an added ```distance_km``` column: the distance (see
+/// [`haversine_km`]) from the requested point to the coordinate of each row. This shows how far the
+/// value is from the requested location, e.g. for the nearest grid cell of ```interpolate=sql```.
+/// Missing coordinates result in a missing distance.
+///
+/// # Arguments
+///
+/// * `df_in` - DataFrame containing the coordinates of the values.
+/// * `requested` - The requested point.
+/// * `lat_col` - The name of the latitude column (e.g. "lat").
+/// * `lon_col` - The name of the longitude column (e.g. "lon").
+///
+pub fn add_haversine_distance(
+    df_in: DataFrame,
+    requested: &Point,
+    lat_col: &str,
+    lon_col: &str,
+) -> std::result::Result<DataFrame, ConnectorError> {
+    let lat = df_in.column(lat_col)?.cast(&DataType::Float64)?;
+    let lon = df_in.column(lon_col)?.cast(&DataType::Float64)?;
+    let distance: Float64Chunked = lat
+        .f64()?
+        .into_iter()
+        .zip(lon.f64()?)
+        .map(|(lat, lon)| match (lat, lon) {
+            (Some(lat), Some(lon)) => Some(haversine_km(requested, &Point { lat, lon })),
+            _ => None,
+        })
+        .collect();
+
+    let mut df_out = df_in;
+    df_out.with_column(distance.into_series().rename("distance_km").clone())?;
+    Ok(df_out)
+}
+
 /// Creates a new DataFrame with added latitude and longitude extracted from the provided ```Point```.
 /// 
 /// # Arguments
 /// 
 /// * `df_in` - DataFrame as derived from the HTTP response, with missing columns for lat/lon
 /// * `point` - The specific point in space (with latitude and longitude) from which to extract the 
-/// lat / lon value for the column.
+///   lat / lon value for the column.
 /// 
 pub async fn df_add_latlon(df_in: polars::frame::DataFrame, point: &Point) -> 
 std::result::Result<polars::frame::DataFrame, polars::error::PolarsError> {
@@ -196,8 +1487,235 @@ std::result::Result<polars::frame::DataFrame, polars::error::PolarsError> {
     Ok(df_out)
 }
 
+/// Combines DataFrames with the same columns (e.g. the results of separate queries for several
+/// points) into one DataFrame. Like ```df_add_postal()```, a constant column with the label of each
+/// frame is put in front of its columns.
+///
+/// # Arguments
+///
+/// * `frames` - The labels and the DataFrames.
+/// * `label_col` - The name of the label column (e.g. "station").
+///
+pub fn concat_labeled(
+    frames: Vec<(String, DataFrame)>,
+    label_col: &str,
+) -> std::result::Result<DataFrame, ConnectorError> {
+    let mut combined: Option<DataFrame> = None;
+    for (label, df) in frames {
+        let labels = Series::new(label_col, vec![label.as_str(); df.height()]);
+        let df = DataFrame::new(vec![labels])?.hstack(df.get_columns())?;
+        match combined.as_mut() {
+            Some(combined) => {
+                combined.vstack_mut(&df)?;
+            }
+            None => combined = Some(df),
+        }
+    }
+    combined.ok_or_else(|| ConnectorError::LibraryError(String::from("no frames to combine")))
+}
+
+/// Brings the identifying columns of a time series DataFrame into one shape, regardless of the
+/// query type. Point queries identify the rows by "lat" and "lon", postal code and station queries
+/// by "station_id" (see ```df_add_postal()```). The result starts with a "location" column (the
+/// station identifier, or the coordinates formatted like a ```Point``` in the query URL, e.g.
+/// "47.423,9.37"), followed by "lat" and "lon" (missing for stations) and the remaining columns. A
+/// DataFrame that already has a "location" column is returned unchanged.
+///
+/// # Arguments
+///
+/// * `df` - The DataFrame with either "lat" and "lon" or "station_id" columns, or both.
+///
+pub fn normalize_location_columns(df: DataFrame) -> std::result::Result<DataFrame, ConnectorError> {
+    let names = df.get_column_names();
+    if names.contains(&"location") {
+        return Ok(df);
+    }
+    let has_latlon = names.contains(&"lat") && names.contains(&"lon");
+    let has_station = names.contains(&"station_id");
+    if !has_latlon && !has_station {
+        return Err(ConnectorError::LibraryError(String::from("column 'lat' and 'lon' or 'station_id' is missing")));
+    }
+
+    let n = df.height();
+    let (lat, lon) = if has_latlon {
+        (df.column("lat")?.cast(&DataType::Float64)?, df.column("lon")?.cast(&DataType::Float64)?)
+    } else {
+        (Series::full_null("lat", n, &DataType::Float64), Series::full_null("lon", n, &DataType::Float64))
+    };
+    let stations: Vec<Option<String>> = if has_station {
+        let station = df.column("station_id")?.cast(&DataType::Utf8)?;
+        let stations = station.utf8()?.into_iter().map(|s| s.map(String::from)).collect();
+        stations
+    } else {
+        vec![None; n]
+    };
+
+    // Rows of a mixed query have either a station identifier or coordinates.
+    let location: Vec<Option<String>> = stations
+        .into_iter()
+        .zip(lat.f64()?.into_iter().zip(lon.f64()?))
+        .map(|(station, coords)| match (station, coords) {
+            (Some(station), _) => Some(station),
+            (None, (Some(lat), Some(lon))) => Some(Point { lat, lon }.to_string()),
+            _ => None,
+        })
+        .collect();
+
+    let mut columns = vec![Series::new("location", location), lat, lon];
+    columns.extend(
+        df.get_columns()
+            .iter()
+            .filter(|col| !matches!(col.name(), "lat" | "lon" | "station_id"))
+            .cloned(),
+    );
+    Ok(DataFrame::new(columns)?)
+}
+
+/// Combines DataFrames with the same rows but different parameters (e.g. the results of one query
+/// split into several requests) column-wise. The columns the frames share (e.g. "lat", "lon" and
+/// "validdate") must be equal and are kept once; the other columns are appended in order.
+///
+/// # Arguments
+///
+/// * `frames` - The DataFrames, in the order of their columns.
+///
+pub fn merge_columns(frames: Vec<DataFrame>) -> std::result::Result<DataFrame, ConnectorError> {
+    let mut frames = frames.into_iter();
+    let mut combined = frames
+        .next()
+        .ok_or_else(|| ConnectorError::LibraryError(String::from("no frames to combine")))?;
+    for df in frames {
+        let mut new_columns = Vec::new();
+        for column in df.get_columns() {
+            match combined.column(column.name()) {
+                Ok(existing) if existing.series_equal_missing(column) => (),
+                Ok(_) => {
+                    return Err(ConnectorError::LibraryError(format!(
+                        "column '{}' differs between the frames", column.name()
+                    )));
+                }
+                Err(_) => new_columns.push(column.clone()),
+            }
+        }
+        combined = combined.hstack(&new_columns)?;
+    }
+    Ok(combined)
+}
+
+/// Computes the east (```u```) and north (```v```) components of the wind from its speed and
+/// direction (e.g. "wind_speed_10m:ms" and "wind_dir_10m:d"), i.e. ```u = -speed * sin(dir)``` and
+/// ```v = -speed * cos(dir)```. The direction is the meteorological direction in degrees the wind is
+/// blowing from. The components have the unit of the speed and are added as the columns ```u``` and
+/// ```v```.
+///
+/// # Arguments
+///
+/// * `df` - DataFrame as returned by one of the query methods (e.g. ```query_time_series()```).
+/// * `speed_col` - The name of the wind speed column.
+/// * `dir_col` - The name of the wind direction column (in degrees).
+///
+pub fn wind_to_uv(
+    df: &DataFrame,
+    speed_col: &str,
+    dir_col: &str,
+) -> std::result::Result<DataFrame, ConnectorError> {
+    for name in [speed_col, dir_col] {
+        match df.column(name) {
+            Ok(column) if column.dtype().is_numeric() => {}
+            Ok(_) => return Err(ConnectorError::LibraryError(format!("column '{}' is not numeric", name))),
+            Err(_) => return Err(ConnectorError::LibraryError(format!("column '{}' is missing", name))),
+        }
+    }
+    let speed = df.column(speed_col)?.cast(&DataType::Float64)?;
+    let dir = df.column(dir_col)?.cast(&DataType::Float64)?;
+    let (u, v): (Vec<Option<f64>>, Vec<Option<f64>>) = speed
+        .f64()?
+        .into_iter()
+        .zip(dir.f64()?)
+        .map(|pair| match pair {
+            (Some(speed), Some(dir)) => {
+                let (sin, cos) = dir.to_radians().sin_cos();
+                (Some(-speed * sin), Some(-speed * cos))
+            }
+            _ => (None, None),
+        })
+        .unzip();
+
+    let mut df = df.clone();
+    df.with_column(Series::new("u", &u))?;
+    df.with_column(Series::new("v", &v))?;
+    Ok(df)
+}
+
+/// Decodes a response body according to the ```charset``` of its ```Content-Type``` (e.g.
+/// "text/csv; charset=iso-8859-1"). Without a charset the body is expected to be UTF-8. Bodies that
+/// are not valid in their charset and unknown charsets are rejected instead of being mangled.
+///
+/// # Arguments
+///
+/// * `bytes` - The raw response body.
+/// * `content_type` - The (lowercase) value of the ```Content-Type``` header, if any.
+///
+pub fn decode_body(bytes: &[u8], content_type: Option<&str>) -> std::result::Result<String, ConnectorError> {
+    let charset = content_type
+        .and_then(|ct| ct.split(';').map(|p| p.trim()).find_map(|p| p.strip_prefix("charset=")))
+        .map(|c| c.trim_matches('"'))
+        .unwrap_or("utf-8");
+    let encoding = encoding_rs::Encoding::for_label(charset.as_bytes())
+        .ok_or_else(|| ConnectorError::LibraryError(format!("unsupported charset '{}'", charset)))?;
+    encoding
+        .decode_without_bom_handling_and_without_replacement(bytes)
+        .map(|body| body.into_owned())
+        .ok_or_else(|| ConnectorError::LibraryError(format!("response body is not valid {}", encoding.name())))
+}
+
+/// Reads the body of a CSV response. Some proxies return an HTML error page with status 200, which
+/// would otherwise be parsed into a garbage DataFrame. Responses with a ```Content-Type``` other than
+/// ```text/csv``` or ```text/plain``` or with a body starting with '<' are rejected with an error that
+/// contains the start of the body. Responses without a ```Content-Type``` are accepted.
+///
+/// # Arguments
+///
+/// * `response` - The HTTP response from the query to the meteomatics API.
+///
+pub async fn read_csv_body(response: Response) -> std::result::Result<String, ConnectorError> {
+    let content_type = content_type(&response);
+    let bytes = response.bytes().await?;
+    let body = decode_body(&bytes, content_type.as_deref())?;
+    check_csv_body(content_type.as_deref(), &body)?;
+    Ok(body)
+}
+
+/// The lowercase value of the ```Content-Type``` header of a response, if any.
+pub(crate) fn content_type(response: &Response) -> Option<String> {
+    response
+        .headers()
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .map(|v| v.to_lowercase())
+}
+
+/// Rejects a response that is not a CSV (see [`read_csv_body`]). Only the start of the body is needed.
+pub(crate) fn check_csv_body(content_type: Option<&str>, body: &str) -> std::result::Result<(), ConnectorError> {
+    let is_csv_type = match content_type {
+        Some(content_type) => content_type.starts_with("text/csv") || content_type.starts_with("text/plain"),
+        None => true,
+    };
+    if !is_csv_type || body.trim_start().starts_with('<') {
+        let prefix: String = body.chars().take(200).collect();
+        return Err(ConnectorError::LibraryError(format!(
+            "unexpected non-CSV response (content type: {}): {}",
+            content_type.unwrap_or("none"),
+            prefix
+        )));
+    }
+    Ok(())
+}
+
 /// Convert the HTTP response into a [`DataFrame`](polars::frame::DataFrame). Consumes the HTTP response.
-/// This is used in all cases where the API response is a tidy CSV.
+/// This is used in all cases where the API response is a tidy CSV. If the query matches no data, the
+/// API returns an empty or header-only CSV and the result is an empty DataFrame (with the columns of
+/// the header, if there is one) instead of an error.
 /// 
 /// # Arguments
 /// 
@@ -205,73 +1723,314 @@ std::result::Result<polars::frame::DataFrame, polars::error::PolarsError> {
 /// 
 pub async fn parse_response_to_df(
     response: Response,
-) -> std::result::Result<polars::frame::DataFrame, polars::error::PolarsError> {
+) -> std::result::Result<polars::frame::DataFrame, ConnectorError> {
+    parse_response_to_df_with_schema(response, None).await
+}
+
+/// Same as [`parse_response_to_df`], but the column types of the schema overwrite the inferred types
+/// (e.g. ```Int64``` for quality flags). Columns that are not in the schema are inferred.
+///
+/// # Arguments
+///
+/// * `response` - The HTTP response from the query to the meteomatics API.
+/// * `schema` - The types of the columns to overwrite (by column name).
+///
+pub async fn parse_response_to_df_with_schema(
+    response: Response,
+    schema: Option<&Schema>,
+) -> std::result::Result<polars::frame::DataFrame, ConnectorError> {
     // Get the response text:
-    let body = response.text().await.unwrap();
+    let body = read_csv_body(response).await?;
+
+    // Queries without data (e.g. no lightning in the period) give an empty or header-only CSV.
+    if let Some(dataframe) = empty_csv_to_df(&body, 0)? {
+        return Ok(dataframe);
+    }
+
+    // Parse the response to a DataFrame
+    let file = std::io::Cursor::new(&body);
+    use polars::prelude::*; 
+    let dataframe = polars::io::csv::CsvReader::new(file)
+        .infer_schema(Some(100))
+        .with_delimiter(detect_delimiter(&body, 0))
+        .with_dtypes(schema)
+        .has_header(true)
+        .with_parse_dates(false)
+        .with_ignore_parser_errors(false)
+        .finish()?;
+
+    Ok(dataframe)
+}
+
+/// Detects the delimiter of a CSV body from its header: ';' (the default of the API) if the header
+/// contains one, otherwise ',' if the header contains one (e.g. some single point queries), otherwise
+/// ';'.
+///
+/// # Arguments
+///
+/// * `body` - The CSV body of the HTTP response.
+/// * `skip_rows` - The number of lines before the header.
+///
+pub(crate) fn detect_delimiter(body: &str, skip_rows: usize) -> u8 {
+    let header = body.lines().filter(|line| !line.trim().is_empty()).nth(skip_rows).unwrap_or("");
+    if !header.contains(';') && header.contains(',') {
+        b','
+    } else {
+        b';'
+    }
+}
+
+/// Returns an empty DataFrame if the CSV body contains no data rows, or ```None``` if it does. An
+/// empty body gives a DataFrame without columns; a header-only body gives the columns of the header
+/// ("validdate" and "station_id" as strings, all others as floats) without rows.
+///
+/// # Arguments
+///
+/// * `body` - The CSV body of the HTTP response.
+/// * `skip_rows` - The number of lines before the header.
+///
+fn empty_csv_to_df(body: &str, skip_rows: usize)
+-> std::result::Result<Option<polars::frame::DataFrame>, ConnectorError> {
+    use polars::prelude::*;
+    let mut lines = body.lines().filter(|line| !line.trim().is_empty()).skip(skip_rows);
+    let header = match lines.next() {
+        Some(header) => header,
+        None => return Ok(Some(DataFrame::default())),
+    };
+    if lines.next().is_some() {
+        return Ok(None);
+    }
+    let columns = header
+        .split(detect_delimiter(header, 0) as char)
+        .map(|name| match name {
+            "validdate" | "station_id" => Series::new(name, Vec::<&str>::new()),
+            _ => Series::new(name, Vec::<f64>::new()),
+        })
+        .collect::<Vec<Series>>();
+    Ok(Some(DataFrame::new(columns)?))
+}
+
+/// Convert the HTTP response into a [`DataFrame`](polars::frame::DataFrame). Consumes the HTTP response.
+/// This is used in cases where the API response is not a tidy CSV.
+/// A response without data rows gives an empty DataFrame (see ```parse_response_to_df()```). For example when downloading a
+/// CSV grid for a single point the returned CSV is pivoted and not in a tidy column-oriented format.
+/// 
+/// # Arguments
+/// 
+/// * `response` - The HTTP response from the query to the meteomatics API.
+/// 
+pub async fn parse_grid_response_to_df(
+    response: Response,
+) -> std::result::Result<polars::frame::DataFrame, ConnectorError> {
+        // Get the response text:
+        let body = read_csv_body(response).await?;
+
+        // Grids without data give an empty or header-only CSV.
+        if let Some(dataframe) = empty_csv_to_df(&body, 2)? {
+            return Ok(dataframe);
+        }
+
+        // Parse the response to a DataFrame
+        let file = std::io::Cursor::new(&body);
+        use polars::prelude::*; 
+        let dataframe = polars::io::csv::CsvReader::new(file)
+            .infer_schema(Some(100))
+            .with_delimiter(detect_delimiter(&body, 2))
+            .has_header(true)
+            .with_skip_rows(2)
+            .with_parse_dates(false)
+            .with_ignore_parser_errors(false)
+            .finish()?;
+    
+        Ok(dataframe)
+}
+
+/// Convert the HTTP response for a pivoted grid into a [`GridMatrix`]. Consumes the HTTP response.
+///
+/// # Arguments
+///
+/// * `response` - The HTTP response from the query to the meteomatics API.
+///
+pub async fn parse_grid_response_to_matrix(
+    response: Response,
+) -> std::result::Result<GridMatrix, ConnectorError> {
+    let body = read_csv_body(response).await?;
+    parse_grid_csv_to_matrix(&body).await
+}
+
+/// Parses the text of a pivoted grid CSV into a [`GridMatrix`]. The CSV starts with a preamble (e.g.
+/// the validdate and parameter), followed by a header line starting with "data" that contains the
+/// longitudes. Every following line contains the latitude and the values for each longitude.
+///
+/// # Arguments
+///
+/// * `body` - The text of the pivoted grid CSV.
+///
+pub async fn parse_grid_csv_to_matrix(body: &str) -> std::result::Result<GridMatrix, ConnectorError> {
+    let parse = |s: &str| s.trim().parse::<f64>().map_err(|_| ConnectorError::ParseError);
+
+    let mut lines = body
+        .lines()
+        .map(|l| l.trim())
+        .filter(|l| !l.is_empty())
+        .skip_while(|l| !l.starts_with("data;"));
+
+    // The header contains the longitudes
+    let header = lines.next().ok_or(ConnectorError::ParseError)?;
+    let lons = header
+        .split(';')
+        .skip(1)
+        .map(parse)
+        .collect::<std::result::Result<Vec<f64>, ConnectorError>>()?;
+
+    let mut lats = Vec::new();
+    let mut values = Vec::new();
+    for line in lines {
+        let mut fields = line.split(';');
+        lats.push(parse(fields.next().ok_or(ConnectorError::ParseError)?)?);
+        let row = fields.map(parse).collect::<std::result::Result<Vec<f64>, ConnectorError>>()?;
+        if row.len() != lons.len() {
+            return Err(ConnectorError::ParseError);
+        }
+        values.push(row);
+    }
+
+    Ok(GridMatrix { lats, lons, values })
+}
+
+/// Convert the HTTP response of a lightning query into a list of [`LightningStroke`]s (without
+/// polars). Consumes the HTTP response.
+///
+/// # Arguments
+///
+/// * `response` - The HTTP response from the lightning query to the meteomatics API.
+///
+pub async fn parse_lightning_response(
+    response: Response,
+) -> std::result::Result<Vec<LightningStroke>, ConnectorError> {
+    let body = read_csv_body(response).await?;
+    parse_lightning_csv(&body).await
+}
+
+/// Parses the text of a lightning CSV into a list of [`LightningStroke`]s. The columns are found by
+/// their names in the header ("stroke_time:sql", "stroke_lat:d" and "stroke_lon:d").
+///
+/// # Arguments
+///
+/// * `body` - The text of the lightning CSV.
+///
+pub async fn parse_lightning_csv(body: &str) -> std::result::Result<Vec<LightningStroke>, ConnectorError> {
+    let mut lines = body.lines().map(|l| l.trim()).filter(|l| !l.is_empty());
+
+    // Find the columns in the header
+    let header: Vec<&str> = lines.next().ok_or(ConnectorError::ParseError)?.split(';').collect();
+    let columns = LightningColumns::original();
+    let index = |name: &str| {
+        header
+            .iter()
+            .position(|h| *h == name)
+            .ok_or_else(|| ConnectorError::LibraryError(format!("column '{}' is missing", name)))
+    };
+    let (i_time, i_lat, i_lon) = (index(&columns.validdate)?, index(&columns.lat)?, index(&columns.lon)?);
 
-    // Parse the response to a DataFrame
-    let file = std::io::Cursor::new(&body);
-    use polars::prelude::*; 
-    let dataframe = polars::io::csv::CsvReader::new(file)
-        .infer_schema(Some(100))
-        .with_delimiter(b';')
-        .has_header(true)
-        .with_parse_dates(false)
-        .with_ignore_parser_errors(false)
-        .finish()?;
+    let mut strokes = Vec::new();
+    for line in lines {
+        let fields: Vec<&str> = line.split(';').collect();
+        let field = |i: usize| fields.get(i).map(|f| f.trim()).ok_or(ConnectorError::ParseError);
+        let time = chrono::DateTime::parse_from_rfc3339(field(i_time)?)
+            .map_err(|_| ConnectorError::ParseError)?
+            .with_timezone(&chrono::Utc);
+        let lat = field(i_lat)?.parse::<f64>().map_err(|_| ConnectorError::ParseError)?;
+        let lon = field(i_lon)?.parse::<f64>().map_err(|_| ConnectorError::ParseError)?;
+        strokes.push(LightningStroke { time, lat, lon });
+    }
 
-    Ok(dataframe)
+    Ok(strokes)
 }
 
-/// Convert the HTTP response into a [`DataFrame`](polars::frame::DataFrame). Consumes the HTTP response.
-/// This is used in cases where the API response is not a tidy CSV. For example when downloading a
-/// CSV grid for a single point the returned CSV is pivoted and not in a tidy column-oriented format.
-/// 
+/// Parses a line of a time series CSV into a [`CsvRow`]. The columns are found by their names in the
+/// header; every column apart from "lat", "lon" and "validdate" is a value.
+///
 /// # Arguments
-/// 
-/// * `response` - The HTTP response from the query to the meteomatics API.
-/// 
-pub async fn parse_grid_response_to_df(
-    response: Response,
-) -> std::result::Result<polars::frame::DataFrame, polars::error::PolarsError> {
-        // Get the response text:
-        let body = response.text().await.unwrap();
+///
+/// * `header` - The column names of the CSV.
+/// * `line` - The line to parse.
+/// * `delimiter` - The field delimiter of the CSV (';' or ',').
+/// * `latlon` - The location of a single-point query, whose response has no "lat" and "lon" columns.
+///
+pub fn parse_csv_row(
+    header: &[String],
+    line: &str,
+    delimiter: char,
+    latlon: Option<(f64, f64)>,
+) -> std::result::Result<CsvRow, ConnectorError> {
+    let fields: Vec<&str> = line.split(delimiter).map(|f| f.trim()).collect();
+    if fields.len() != header.len() {
+        return Err(ConnectorError::ParseError);
+    }
+    let field = |name: &str| header.iter().position(|h| h == name).map(|i| fields[i]);
+    let coordinate = |name: &str| -> std::result::Result<f64, ConnectorError> {
+        field(name)
+            .ok_or_else(|| ConnectorError::LibraryError(format!("column '{}' is missing", name)))?
+            .parse::<f64>()
+            .map_err(|_| ConnectorError::ParseError)
+    };
+    let (lat, lon) = match latlon {
+        Some(latlon) => latlon,
+        None => (coordinate("lat")?, coordinate("lon")?),
+    };
+    let validdate = field("validdate")
+        .ok_or_else(|| ConnectorError::LibraryError(String::from("column 'validdate' is missing")))?;
+    let validdate = chrono::DateTime::parse_from_rfc3339(validdate)
+        .map_err(|_| ConnectorError::ParseError)?
+        .with_timezone(&chrono::Utc);
+    let values = header
+        .iter()
+        .zip(&fields)
+        .filter(|(name, _)| !matches!(name.as_str(), "lat" | "lon" | "validdate"))
+        .map(|(_, value)| value.parse::<f64>().ok().filter(|v| v.is_finite()))
+        .collect();
+    Ok(CsvRow { lat, lon, validdate, values })
+}
 
-        // Parse the response to a DataFrame
-        let file = std::io::Cursor::new(&body);
-        use polars::prelude::*; 
-        let dataframe = polars::io::csv::CsvReader::new(file)
-            .infer_schema(Some(100))
-            .with_delimiter(b';')
-            .has_header(true)
-            .with_skip_rows(2)
-            .with_parse_dates(false)
-            .with_ignore_parser_errors(false)
-            .finish()?;
-    
-        Ok(dataframe)
+/// Formats the reference period of climate normals as an optional parameter for the query URL
+/// (e.g. ```(1991, 2020)``` becomes "climate_period=1991-2020"). The start year has to be before the
+/// end year.
+///
+/// # Arguments
+///
+/// * `period` - The first and the last year of the reference period (e.g. ```(1991, 2020)```).
+///
+pub fn climate_period_optional(period: (i32, i32)) -> std::result::Result<String, ConnectorError> {
+    let (start, end) = period;
+    if start >= end {
+        return Err(ConnectorError::LibraryError(format!(
+            "the start year of the climate period ({}) must be before the end year ({})", start, end
+        )));
+    }
+    Ok(format!("climate_period={}-{}", start, end))
 }
 
 /// Builds the query specifications ('specs') for a time series query according to the Meteomatics API
 /// format rules. Optionally parses a number of provided extra specifiers (e.g. 'model=mix'). The
 /// dates are formatted according to ISO8601 (<https://en.wikipedia.org/wiki/ISO_8601>). The format
-/// parameter specifies the requested file type (e.g. "csv" or "netcdf" or "png").
+/// parameter specifies the requested file type (see [`Format`]).
 /// 
 /// # Arguments
 /// 
 /// * `time_series` - Defines the temporal extent (time and date of start and a timedelta).
 /// * `parameters` - Names of individual parameters (e.g. "t_2m:C", "wind_speed_10m:ms"). 
 /// * `coords_str` - Specifies the locations for the API (formatted according to the API rules, e.g.
-/// '47.0,8+46.5,9')
+///   '47.0,8+46.5,9')
 /// * `optionals` - Optional parameters for the request (e.g. "calibrated=true").
-/// * `format` - Specifies the file format for the request (e.g. "csv" or "netcdf")
+/// * `format` - Specifies the file format for the request (e.g. ```Format::Csv``` or ```Format::NetCdf```)
 /// 
 pub async fn build_ts_query_specs(
     time_series: &TimeSeries,
     parameters: &[String],
     coords_str: &str,
     optionals: &Option<Vec<String>>,
-    format: &str,
+    format: Format,
 ) -> String {
     let query_specs = format!(
         "{}/{}/{}/{}",
@@ -296,30 +2055,111 @@ pub async fn build_ts_query_specs(
     query_specs
 }
 
+/// Same as [`build_ts_query_specs`], but renders the dates with the given precision (e.g.
+/// ```SecondsFormat::Secs``` to truncate sub-second timestamps, see [`format_timestamp`]). Fails for
+/// a time series without a timedelta.
+///
+pub async fn build_ts_query_specs_with_precision(
+    time_series: &TimeSeries,
+    parameters: &[String],
+    coords_str: &str,
+    optionals: &Option<Vec<String>>,
+    format: Format,
+    precision: chrono::SecondsFormat,
+) -> std::result::Result<String, ConnectorError> {
+    let query_specs = format!(
+        "{}/{}/{}/{}",
+        time_series.to_string_with_precision(precision)?,
+        parameters.join(","),
+        coords_str,
+        format
+    );
+
+    // Handles optional parameters 
+    match optionals {
+        None => Ok(query_specs),
+        Some(optionals) => Ok(format!("{}?{}", query_specs, optionals.join("&"))),
+    }
+}
+
+/// Builds the query specifications ('specs') for a time series query with an explicit list of dates
+/// instead of a start/end/step range. The dates are joined by ',' and are formatted according to
+/// ISO8601 with the given precision (see [`format_timestamp`]).
+///
+/// # Arguments
+///
+/// * `dates` - The points in time for the request (e.g. only the daily noon values of some days).
+/// * `parameters` - Names of individual parameters (e.g. "t_2m:C", "wind_speed_10m:ms").
+/// * `coords_str` - Specifies the locations for the API (formatted according to the API rules, e.g.
+///   '47.0,8+46.5,9')
+/// * `optionals` - Optional parameters for the request (e.g. "calibrated=true").
+/// * `format` - Specifies the file format for the request (e.g. ```Format::Csv``` or ```Format::NetCdf```)
+/// * `precision` - The precision of the fractional seconds (e.g. ```SecondsFormat::AutoSi```).
+///
+pub async fn build_dates_query_specs(
+    dates: &[chrono::DateTime<chrono::Utc>],
+    parameters: &[String],
+    coords_str: &str,
+    optionals: &Option<Vec<String>>,
+    format: Format,
+    precision: chrono::SecondsFormat,
+) -> String {
+    let dates_str = dates.iter().map(|d| format_timestamp(d, precision)).collect::<Vec<String>>().join(",");
+    let query_specs = format!(
+        "{}/{}/{}/{}",
+        dates_str,
+        parameters.join(","),
+        coords_str,
+        format
+    );
+
+    // Handles optional parameters 
+    match optionals {
+        None => query_specs,
+        Some(optionals) => format!("{}?{}", query_specs, optionals.join("&")),
+    }
+}
+
 /// Builds the query specifications ('specs') for a grid query according to the Meteomatics API
 /// format rules. Optionally parses a number of provided extra specifiers (e.g. 'model=mix'). The
 /// date is formatted according to ISO8601 (<https://en.wikipedia.org/wiki/ISO_8601>). The format
-/// parameter specifies the requested file type (e.g. "csv" or "netcdf" or "png").
+/// parameter specifies the requested file type (see [`Format`]).
 /// 
 /// # Arguments
 /// 
 /// * `timestamp` - Date and time for the request.
 /// * `parameter` - Name of an individual parameter (e.g. "t_2m:C" or "wind_speed_10m:ms"). 
 /// * `coords_str` - Specifies the locations for the API (formatted according to the API rules, e.g.
-/// '47.0,8+46.5,9')
+///   '47.0,8+46.5,9')
 /// * `optionals` - Optional parameters for the request (e.g. "calibrated=true").
-/// * `format` - Specifies the file format for the request (e.g. "csv" or "netcdf")
+/// * `format` - Specifies the file format for the request (e.g. ```Format::Csv``` or ```Format::NetCdf```)
 /// 
 pub async fn build_grid_query_specs(
     timestamp: &chrono::DateTime<chrono::Utc>,
     parameter: &String,
     coords_str: &str,
     optionals: &Option<Vec<String>>,
-    format: &str,
+    format: Format,
+) -> String {
+    build_grid_query_specs_with_precision(
+        timestamp, parameter, coords_str, optionals, format, chrono::SecondsFormat::AutoSi
+    ).await
+}
+
+/// Same as [`build_grid_query_specs`], but renders the date with the given precision (e.g.
+/// ```SecondsFormat::Secs``` to truncate sub-second timestamps, see [`format_timestamp`]).
+///
+pub async fn build_grid_query_specs_with_precision(
+    timestamp: &chrono::DateTime<chrono::Utc>,
+    parameter: &String,
+    coords_str: &str,
+    optionals: &Option<Vec<String>>,
+    format: Format,
+    precision: chrono::SecondsFormat,
 ) -> String {
     let query_specs = format!(
         "{}/{}/{}/{}",
-        timestamp.to_rfc3339(),
+        format_timestamp(timestamp, precision),
         parameter,
         coords_str,
         format
@@ -343,22 +2183,22 @@ pub async fn build_grid_query_specs(
 /// Builds the query specifications ('specs') for a time series grid query according to the Meteomatics 
 /// API format rules. Optionally parses a number of provided extra specifiers (e.g. 'model=mix'). The
 /// dates are formatted according to ISO8601 (<https://en.wikipedia.org/wiki/ISO_8601>). The format
-/// parameter specifies the requested file type (e.g. "csv" or "netcdf" or "png").
+/// parameter specifies the requested file type (see [`Format`]).
 /// 
 ///  # Arguments
 /// 
 /// * `time_series` - Defines the temporal extent (time and date of start and a timedelta).
 /// * `parameter` - Name of an individual parameter (e.g. "t_2m:C" or "wind_speed_10m:ms"). 
 /// * `coords_str` - Specifies the locations for the API (formatted according to the API rules, e.g.
-/// '47.0,8+46.5,9')
-/// * `format` - Specifies the file format for the request (e.g. "csv" or "netcdf")
+///   '47.0,8+46.5,9')
+/// * `format` - Specifies the file format for the request (e.g. ```Format::Csv``` or ```Format::NetCdf```)
 /// * `optionals` - Optional parameters for the request (e.g. "calibrated=true").
 /// 
 pub async fn build_grid_ts_query_specs(
     time_series: &TimeSeries,
     parameter: &String,
     coords_str: &str,
-    format: &str,
+    format: Format,
     optionals: &Option<Vec<String>>,
 ) -> String {
     let query_specs = format!(
@@ -384,6 +2224,33 @@ pub async fn build_grid_ts_query_specs(
     query_specs
 }
 
+/// Same as [`build_grid_ts_query_specs`], but renders the dates with the given precision (e.g.
+/// ```SecondsFormat::Secs``` to truncate sub-second timestamps, see [`format_timestamp`]). Fails for
+/// a time series without a timedelta.
+///
+pub async fn build_grid_ts_query_specs_with_precision(
+    time_series: &TimeSeries,
+    parameter: &String,
+    coords_str: &str,
+    format: Format,
+    optionals: &Option<Vec<String>>,
+    precision: chrono::SecondsFormat,
+) -> std::result::Result<String, ConnectorError> {
+    let query_specs = format!(
+        "{}/{}/{}/{}",
+        time_series.to_string_with_precision(precision)?,
+        parameter,
+        coords_str,
+        format,
+    );
+
+    // Handles optional parameters 
+    match optionals {
+        None => Ok(query_specs),
+        Some(optionals) => Ok(format!("{}?{}", query_specs, optionals.join("&"))),
+    }
+}
+
 /// This query is used to get information about lightning in a defined area and over a certain amount
 /// of time (defined by ```start_date``` and ```end_date```).
 /// 
@@ -391,19 +2258,43 @@ pub async fn build_grid_ts_query_specs(
 /// 
 /// * `time_series` - Defines the temporal extent (time and date of start and a timedelta).
 /// * `coords_str` - Specifies the locations for the API (formatted according to the API rules, e.g.
-/// '47.0,8+46.5,9')
+///   '47.0,8+46.5,9')
 /// 
 pub async fn build_grid_ts_lightning_query_specs(
     time_series: &TimeSeries,
     coords_str: &str
 ) -> String {
-    let query_specs = format!(
+    build_grid_ts_lightning_query_specs_with_precision(time_series, coords_str, chrono::SecondsFormat::AutoSi).await
+}
+
+/// Same as [`build_grid_ts_lightning_query_specs`], but renders the start and the end with the given
+/// precision (see [`format_timestamp`]).
+///
+pub async fn build_grid_ts_lightning_query_specs_with_precision(
+    time_series: &TimeSeries,
+    coords_str: &str,
+    precision: chrono::SecondsFormat,
+) -> String {
+    format!(
         "get_lightning_list?time_range={}--{}&bounding_box={}&format=csv",
-        time_series.start.to_rfc3339(),
-        time_series.end.to_rfc3339(),
+        format_timestamp(&time_series.start, precision),
+        format_timestamp(&time_series.end, precision),
         coords_str
-    );
-    query_specs 
+    )
+}
+
+/// Creates the dates and locations strings of a route query from a list of stops. The n-th date
+/// belongs to the n-th location.
+///
+/// # Arguments
+///
+/// * `stops` - The stops of the route (see [`crate::location::RouteStop`]).
+/// * `precision` - The precision of the fractional seconds (e.g. ```SecondsFormat::AutoSi```).
+///
+pub fn format_route_stops(stops: &[RouteStop], precision: chrono::SecondsFormat) -> (String, String) {
+    let dates_str = stops.iter().map(|s| format_timestamp(&s.when, precision)).collect::<Vec<String>>().join(",");
+    let points_str = stops.iter().map(|s| s.location.to_string()).collect::<Vec<String>>().join("+");
+    (dates_str, points_str)
 }
 
 /// Creates the query specs for the route query type.
@@ -413,7 +2304,7 @@ pub async fn build_grid_ts_lightning_query_specs(
 /// * `dates` - These dates specify the points in time for the respective locations. 
 /// * `parameters` - Names of individual parameters (e.g. "t_2m:C" or "wind_speed_10m:ms").
 /// * `coords_str` - Specifies the locations for the API (formatted according to the API rules, e.g.
-/// '47.0,8+46.5,9')
+///   '47.0,8+46.5,9')
 /// 
 pub async fn build_route_query_specs(
     dates: &str,
@@ -429,29 +2320,574 @@ pub async fn build_route_query_specs(
     query_specs
 }
 
-/// Combines the default base API URL with the query specific information.
-pub async fn build_url(url_fragment: &str) -> std::result::Result<Url, ParseError> {
-    let base_url = Url::parse(BASE_URL).expect("Base URL is known to be valid");
-    let full_url = base_url.join(url_fragment)?;
-    Ok(full_url)
-}
+/// Parses the ```Retry-After``` header of a response (e.g. of a ```429 Too Many Requests```). The
+/// header either contains the number of seconds to wait or a HTTP date. Returns ```None``` if the
+/// header is missing or cannot be parsed.
+///
+/// # Arguments
+///
+/// * `response` - The HTTP response as returned by the API.
+///
+pub fn parse_retry_after(response: &Response) -> Option<std::time::Duration> {
+    let value = response.headers().get(reqwest::header::RETRY_AFTER)?.to_str().ok()?.trim();
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Some(std::time::Duration::from_secs(seconds));
+    }
+    let date = chrono::DateTime::parse_from_rfc2822(value).ok()?;
+    // A date in the past means that the request can be retried right away.
+    let wait = date.with_timezone(&chrono::Utc) - chrono::Utc::now();
+    Some(wait.to_std().unwrap_or_default())
+}
+
+/// Turns an unsuccessful response into the matching error: ```AuthError``` for rejected credentials
+/// (HTTP 401 and 403), ```RateLimited``` with the parsed ```Retry-After``` header for HTTP 429 and
+/// ```HttpError``` with the body of the response for every other status. Consumes the HTTP response.
+///
+/// # Arguments
+///
+/// * `response` - The HTTP response as returned by the API.
+///
+pub async fn http_error(response: Response) -> ConnectorError {
+    #[cfg(feature = "tracing")]
+    let response_url = response.url().clone();
+    let status = response.status();
+    if status == reqwest::StatusCode::TOO_MANY_REQUESTS {
+        return ConnectorError::RateLimited { retry_after: parse_retry_after(&response) };
+    }
+    let body = response.text().await.unwrap_or_default();
+    #[cfg(feature = "tracing")]
+    tracing::warn!(url = %response_url, status = %status, body = %body, "request failed");
+    match status {
+        reqwest::StatusCode::UNAUTHORIZED | reqwest::StatusCode::FORBIDDEN => ConnectorError::AuthError(format!(
+            "{}: please check the username and password (or the token) of the client. {}", status, body
+        )),
+        _ => ConnectorError::HttpError(status.to_string(), body, status),
+    }
+}
+
+/// Names of parameters that are accepted without a unit by [`validate_parameter`]. These are the
+/// categorical weather symbol and weather code parameters, whose values are codes rather than
+/// physical quantities.
+pub const UNITLESS_PARAMETERS: &[&str] = &[
+    "weather_symbol_1h",
+    "weather_symbol_3h",
+    "weather_symbol_6h",
+    "weather_symbol_12h",
+    "weather_symbol_24h",
+    "weather_code_1h",
+    "weather_code_3h",
+    "weather_code_6h",
+    "weather_code_12h",
+    "weather_code_24h",
+];
+
+/// Checks that a parameter follows the "name:unit" grammar of the API (e.g. "t_2m:C" or
+/// "wind_speed_10m:ms") before it is sent. The name and the unit must not be empty and may only
+/// contain ASCII letters, digits, '_' and '-'. Parameters listed in [`UNITLESS_PARAMETERS`] may omit
+/// the unit.
+///
+/// # Arguments
+///
+/// * `parameter` - The name of the parameter (e.g. "t_2m:C").
+///
+pub fn validate_parameter(parameter: &str) -> std::result::Result<(), ConnectorError> {
+    let valid_part = |s: &str| !s.is_empty() && s.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+    if parameter.chars().any(char::is_whitespace) {
+        return Err(ConnectorError::LibraryError(format!("parameter '{}' contains whitespace", parameter)));
+    }
+    let is_valid = match parameter.split_once(':') {
+        Some((name, unit)) => valid_part(name) && valid_part(unit),
+        None if UNITLESS_PARAMETERS.contains(&parameter) => true,
+        None => {
+            return Err(ConnectorError::LibraryError(
+                format!("parameter '{}' has no unit (expected 'name:unit', e.g. 't_2m:C')", parameter)
+            ));
+        }
+    };
+    if is_valid {
+        Ok(())
+    } else {
+        Err(ConnectorError::LibraryError(format!("parameter '{}' is malformed (expected 'name:unit')", parameter)))
+    }
+}
+
+/// Checks a number of parameters with [`validate_parameter`] and reports the first malformed one.
+pub fn validate_parameters(parameters: &[String]) -> std::result::Result<(), ConnectorError> {
+    parameters.iter().try_for_each(|p| validate_parameter(p))
+}
+
+/// Registry of the canonical units of the API together with common variants (compared in lower case)
+/// that are corrected by [`normalize_unit`]. The units are grouped by quantity.
+pub const UNIT_REGISTRY: &[(&str, &[&str])] = &[
+    // temperature
+    ("C", &["c", "degc", "°c", "celsius"]),
+    ("F", &["f", "degf", "°f", "fahrenheit"]),
+    ("K", &["k", "kelvin"]),
+    // speed
+    ("ms", &["m/s", "mps", "m_s"]),
+    ("kmh", &["km/h", "kph", "kmph"]),
+    ("kn", &["kt", "kts", "knots"]),
+    ("bft", &["beaufort"]),
+    // length and precipitation
+    ("mm", &["millimeter", "millimetre"]),
+    ("cm", &["centimeter", "centimetre"]),
+    ("m", &["meter", "metre"]),
+    ("km", &["kilometer", "kilometre"]),
+    ("ft", &["feet", "foot"]),
+    // pressure
+    ("hPa", &["hpa", "mbar", "mb", "millibar"]),
+    ("Pa", &["pa", "pascal"]),
+    // ratio, direction and time
+    ("p", &["%", "percent", "pct"]),
+    ("d", &["deg", "degree", "degrees"]),
+    ("s", &["sec", "second", "seconds"]),
+    ("min", &["minute", "minutes"]),
+    ("h", &["hour", "hours"]),
+    // energy and power
+    ("W", &["w", "watt"]),
+    ("J", &["j", "joule"]),
+    // index and dates
+    ("idx", &["index"]),
+    ("sql", &[]),
+];
+
+/// Normalizes the unit of a parameter to its canonical form (e.g. "t_2m:c" or "t_2m:Celsius" become
+/// "t_2m:C") using the [`UNIT_REGISTRY`]. Returns an error for malformed parameters (see
+/// [`validate_parameter`]) and for units that cannot be recognized.
+///
+/// # Arguments
+///
+/// * `param` - The name of the parameter (e.g. "t_2m:C").
+///
+pub fn normalize_unit(param: &str) -> std::result::Result<String, ConnectorError> {
+    let (name, unit) = match param.split_once(':') {
+        Some(parts) => parts,
+        None => {
+            // Unit-less parameters are returned unchanged, everything else is reported.
+            validate_parameter(param)?;
+            return Ok(param.to_string());
+        }
+    };
+    let unit_lower = unit.to_lowercase();
+    let canonical = UNIT_REGISTRY
+        .iter()
+        .find(|(canonical, _)| *canonical == unit)
+        .or_else(|| UNIT_REGISTRY.iter().find(|(_, variants)| variants.contains(&unit_lower.as_str())))
+        .map(|(canonical, _)| *canonical)
+        .ok_or_else(|| ConnectorError::LibraryError(format!("unknown unit '{}' of parameter '{}'", unit, param)))?;
+
+    let normalized = format!("{}:{}", name, canonical);
+    validate_parameter(&normalized)?;
+    Ok(normalized)
+}
+
+/// Splits a parameter or a column header into the name and the unit (e.g. "t_2m:C" becomes "t_2m"
+/// and "C"). Headers without a unit (e.g. "validdate") have no unit.
+///
+/// # Arguments
+///
+/// * `header` - The parameter or column header (e.g. "t_2m:C").
+///
+pub fn split_param_unit(header: &str) -> (String, Option<String>) {
+    match header.split_once(':') {
+        Some((name, unit)) => (name.to_string(), Some(unit.to_string())),
+        None => (header.to_string(), None),
+    }
+}
+
+/// Maps every parameter column of a DataFrame to its unit (e.g. "t_2m:C" to "C"), parsed from the
+/// column headers with [`split_param_unit`]. The map is keyed by the full column name, since the same
+/// parameter can be requested in several units (e.g. "t_2m:C" and "t_2m:F"). Columns without a unit
+/// (e.g. "lat", "lon" or "validdate") are skipped.
+///
+/// # Arguments
+///
+/// * `df` - DataFrame as returned by one of the query methods (e.g. ```query_time_series()```).
+///
+pub fn parameter_units(df: &DataFrame) -> std::collections::HashMap<String, String> {
+    df.get_column_names()
+        .iter()
+        .filter_map(|header| match split_param_unit(header) {
+            (_, Some(unit)) => Some((header.to_string(), unit)),
+            (_, None) => None,
+        })
+        .collect()
+}
+
+/// Combines the default base API URL with the query specific information.
+pub async fn build_url(url_fragment: &str) -> std::result::Result<Url, ParseError> {
+    let base_url = Url::parse(BASE_URL).expect("Base URL is known to be valid");
+    let full_url = base_url.join(url_fragment)?;
+    Ok(full_url)
+}
+
+/// Convert a list of mixed locations (points, postal codes and stations) to a String according to the
+/// Meteomatics API specifications.
+pub fn locations_to_str(locations: &[Location]) -> String {
+    locations.iter().map(|l| l.to_string()).collect::<Vec<String>>().join("+")
+}
+
+/// Convert a number of Points to a String according to the Meteomatics API specifications.
+pub async fn points_to_str(coords: &[Point]) -> String {
+    coords.iter().map(|p| format!("{}", p)).collect::<Vec<String>>().join("+")
+}
+
+/// Compares two DataFrames, allowing for small floating point differences. The frames are equal if
+/// they have the same column names in the same order, the same number of rows, numeric columns that
+/// differ by at most ```epsilon``` per value and identical other columns. Missing values have to be
+/// missing in both frames.
+///
+/// # Arguments
+///
+/// * `a` - The first DataFrame.
+/// * `b` - The second DataFrame.
+/// * `epsilon` - The largest allowed absolute difference between two numeric values.
+///
+pub fn frames_approx_equal(a: &DataFrame, b: &DataFrame, epsilon: f64) -> bool {
+    if a.get_column_names() != b.get_column_names() || a.height() != b.height() {
+        return false;
+    }
+
+    a.get_columns().iter().zip(b.get_columns()).all(|(col_a, col_b)| {
+        if !(col_a.dtype().is_numeric() && col_b.dtype().is_numeric()) {
+            return col_a.series_equal_missing(col_b);
+        }
+        match (col_a.cast(&DataType::Float64), col_b.cast(&DataType::Float64)) {
+            (Ok(col_a), Ok(col_b)) => match (col_a.f64(), col_b.f64()) {
+                (Ok(values_a), Ok(values_b)) => values_a.into_iter().zip(values_b).all(|pair| match pair {
+                    (Some(x), Some(y)) => (x - y).abs() <= epsilon,
+                    (None, None) => true,
+                    _ => false,
+                }),
+                _ => false,
+            },
+            _ => false,
+        }
+    })
+}
+
+/// Renders the values of a single column as a one-line unicode sparkline (e.g. "▁▃▅█▆"). This is
+/// meant for quick debugging in the terminal. Every row is rendered as one character, missing values
+/// are rendered as a blank gap.
+///
+/// # Arguments
+///
+/// * `df` - DataFrame as returned by one of the query methods (e.g. ```query_time_series()```).
+/// * `value_col` - The name of the column to render (e.g. "t_2m:C").
+///
+pub fn sparkline(df: &DataFrame, value_col: &str) -> std::result::Result<String, ConnectorError> {
+    const TICKS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+    let values = df.column(value_col)?.cast(&DataType::Float64)?;
+    let values = values.f64()?;
+
+    // Get the range of the (non-null) values
+    let min = values.into_iter().flatten().fold(f64::INFINITY, f64::min);
+    let max = values.into_iter().flatten().fold(f64::NEG_INFINITY, f64::max);
+    let range = max - min;
+
+    let line = values
+        .into_iter()
+        .map(|v| match v {
+            Some(v) if range > 0.0 => {
+                let idx = ((v - min) / range * (TICKS.len() - 1) as f64).round() as usize;
+                TICKS[idx]
+            }
+            Some(_) => TICKS[0],
+            None => ' ',
+        })
+        .collect::<String>();
+
+    Ok(line)
+}
+
+#[cfg(test)]
+mod tests {
+
+    use chrono::{TimeZone, Utc};
+    use chrono::Duration;
+    use crate::location::{Point, BBox};
+    use crate::connector_components::Format;
+    use std::path::Path;
+    use std::fs;
+    use serde_json;
+    use crate::util::{UStatsResponse, TimeSeries};
+    use polars::prelude::*;
+
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn check_time_series_serde_round_trip() {
+        let start = Utc.ymd(1989, 11, 9).and_hms(18, 0, 0);
+        let time_series = TimeSeries { start, end: start + Duration::days(1), timedelta: Some(Duration::hours(12)) };
+        let json = serde_json::to_string(&time_series).unwrap();
+        assert!(json.contains(r#""timedelta":43200000"#));
+        let back: TimeSeries = serde_json::from_str(&json).unwrap();
+        assert_eq!((back.start, back.end, back.timedelta), (time_series.start, time_series.end, time_series.timedelta));
+
+        let no_step: TimeSeries = serde_json::from_str(
+            r#"{"start":"1989-11-09T18:00:00Z","end":"1989-11-09T18:00:00Z","timedelta":null}"#
+        ).unwrap();
+        assert_eq!(no_step.timedelta, None);
+    }
+
+    #[tokio::test]
+    async fn check_empty_csv_response() {
+        let url = url::Url::parse("https://api.meteomatics.com/").unwrap();
+
+        let response = crate::cache::response_from_body(url.clone(), Vec::new());
+        let df = crate::util::parse_response_to_df(response).await.unwrap();
+        assert_eq!(df.shape(), (0, 0));
+
+        let response = crate::cache::response_from_body(url.clone(), b"stroke_time:sql;stroke_lat:d;stroke_lon:d\n".to_vec());
+        let df = crate::util::parse_response_to_df(response).await.unwrap();
+        assert_eq!(df.shape(), (0, 3));
+        assert_eq!(df.get_column_names(), &["stroke_time:sql", "stroke_lat:d", "stroke_lon:d"]);
+
+        let response = crate::cache::response_from_body(url.clone(), b"validdate;t_2m:C\n".to_vec());
+        let df = crate::util::parse_response_to_df(response).await.unwrap();
+        assert_eq!(df.column("validdate").unwrap().dtype(), &DataType::Utf8);
+        assert_eq!(df.column("t_2m:C").unwrap().dtype(), &DataType::Float64);
+
+        let response = crate::cache::response_from_body(url, b"\n\n".to_vec());
+        let df = crate::util::parse_grid_response_to_df(response).await.unwrap();
+        assert_eq!(df.shape(), (0, 0));
+    }
+
+    #[test]
+    fn check_climate_period_optional() {
+        assert_eq!(crate::util::climate_period_optional((1991, 2020)).unwrap(), "climate_period=1991-2020");
+        assert!(crate::util::climate_period_optional((2020, 1991)).is_err());
+        assert!(crate::util::climate_period_optional((1991, 1991)).is_err());
+    }
+
+    #[test]
+    fn check_complete_time_series() {
+        let start = Utc.ymd(1989, 11, 9).and_hms(18, 0, 0);
+        let dates = vec![start, start + Duration::hours(1), start + Duration::hours(2)];
+        let coordinates = vec![Point { lat: 47.0, lon: 9.0 }, Point { lat: 46.5, lon: 8.5 }];
+
+        // Gappy response: the second location has no value at 19:00 and the first none at 20:00.
+        let df = df!(
+            "lat" => &[47.0, 47.0, 46.5, 46.5],
+            "lon" => &[9.0, 9.0, 8.5, 8.5],
+            "validdate" => &["1989-11-09T18:00:00Z", "1989-11-09T19:00:00Z", "1989-11-09T18:00:00Z", "1989-11-09T20:00:00Z"],
+            "t_2m:C" => &[5.1, 4.8, 3.2, 2.9]
+        ).unwrap();
+        let df = crate::util::complete_time_series(&df, &dates, &coordinates).unwrap();
+        assert_eq!(df.shape(), (6, 4));
+        let lats: Vec<Option<f64>> = df.column("lat").unwrap().f64().unwrap().into_iter().collect();
+        assert_eq!(lats, vec![Some(47.0), Some(47.0), Some(47.0), Some(46.5), Some(46.5), Some(46.5)]);
+        let validdates: Vec<Option<&str>> = df.column("validdate").unwrap().utf8().unwrap().into_iter().collect();
+        assert_eq!(validdates[2], Some("1989-11-09T20:00:00Z"));
+        assert_eq!(validdates[4], Some("1989-11-09T19:00:00Z"));
+        let values: Vec<Option<f64>> = df.column("t_2m:C").unwrap().f64().unwrap().into_iter().collect();
+        assert_eq!(values, vec![Some(5.1), Some(4.8), None, Some(3.2), None, Some(2.9)]);
+
+        // The server echoes the coordinates rounded to 6 decimals and may format the dates differently.
+        let precise = vec![Point { lat: 47.4239384, lon: 9.3728576 }, Point { lat: 46.5, lon: 8.5 }];
+        let df = df!(
+            "lat" => &[47.423938, 46.5],
+            "lon" => &[9.372858, 8.5],
+            "validdate" => &["1989-11-09T18:00:00+00:00", "1989-11-09T19:00:00.000Z"],
+            "t_2m:C" => &[5.1, 3.2]
+        ).unwrap();
+        let df = crate::util::complete_time_series(&df, &dates, &precise).unwrap();
+        let values: Vec<Option<f64>> = df.column("t_2m:C").unwrap().f64().unwrap().into_iter().collect();
+        assert_eq!(values, vec![Some(5.1), None, None, None, Some(3.2), None]);
+
+        let no_dates = df!("lat" => &[47.0], "lon" => &[9.0]).unwrap();
+        assert!(crate::util::complete_time_series(&no_dates, &dates, &coordinates).is_err());
+    }
+
+    #[cfg(feature = "tz")]
+    #[test]
+    fn check_validdate_to_tz() {
+        // Winter (CET, +01:00) and summer time (CEST, +02:00)
+        let df = df!(
+            "validdate" => &["1989-11-09T18:00:00Z", "2022-07-01T12:00:00Z"],
+            "t_2m:C" => &[5.1, 24.3]
+        ).unwrap();
+        let df = crate::util::validdate_to_tz(df, chrono_tz::Europe::Zurich).unwrap();
+        let dates: Vec<Option<&str>> = df.column("validdate").unwrap().utf8().unwrap().into_iter().collect();
+        assert_eq!(dates, vec![Some("1989-11-09T19:00:00+01:00"), Some("2022-07-01T14:00:00+02:00")]);
+
+        let invalid = df!("validdate" => &["yesterday"]).unwrap();
+        assert!(crate::util::validdate_to_tz(invalid, chrono_tz::Europe::Zurich).is_err());
+    }
+
+    #[tokio::test]
+    async fn check_csv_delimiter_detection() {
+        let url = url::Url::parse("https://api.meteomatics.com/").unwrap();
+        let semicolon = b"validdate;t_2m:C;precip_1h:mm\n1989-11-09T18:00:00Z;5.1;0.0\n1989-11-09T19:00:00Z;4.8;0.2\n";
+        let comma = b"validdate,t_2m:C,precip_1h:mm\n1989-11-09T18:00:00Z,5.1,0.0\n1989-11-09T19:00:00Z,4.8,0.2\n";
+        let df_semicolon = crate::util::parse_response_to_df(
+            crate::cache::response_from_body(url.clone(), semicolon.to_vec())
+        ).await.unwrap();
+        let df_comma = crate::util::parse_response_to_df(
+            crate::cache::response_from_body(url.clone(), comma.to_vec())
+        ).await.unwrap();
+        assert_eq!(df_semicolon.shape(), (2, 3));
+        assert!(df_semicolon.frame_equal_missing(&df_comma));
+
+        // Header-only bodies and grids (with two lines before the header) are detected as well.
+        let df = crate::util::parse_response_to_df(
+            crate::cache::response_from_body(url.clone(), b"validdate,t_2m:C\n".to_vec())
+        ).await.unwrap();
+        assert_eq!(df.get_column_names(), &["validdate", "t_2m:C"]);
+        let grid_semicolon = b"t_2m:C\nvaliddate: 1989-11-09T18:00:00Z\ndata;13.4;13.5\n52.5;5.1;5.2\n52.4;4.9;5.0\n";
+        let grid_comma = b"t_2m:C\nvaliddate: 1989-11-09T18:00:00Z\ndata,13.4,13.5\n52.5,5.1,5.2\n52.4,4.9,5.0\n";
+        let df_semicolon = crate::util::parse_grid_response_to_df(
+            crate::cache::response_from_body(url.clone(), grid_semicolon.to_vec())
+        ).await.unwrap();
+        let df_comma = crate::util::parse_grid_response_to_df(
+            crate::cache::response_from_body(url, grid_comma.to_vec())
+        ).await.unwrap();
+        assert_eq!(df_semicolon.shape(), (2, 3));
+        assert!(df_semicolon.frame_equal_missing(&df_comma));
+    }
+
+    #[test]
+    fn check_merge_columns() {
+        let a = df!("lat" => [47.0, 47.0], "validdate" => ["1989-11-09T18:00:00Z", "1989-11-10T18:00:00Z"], "t_2m:C" => [1.0, 2.0]).unwrap();
+        let b = df!("lat" => [47.0, 47.0], "validdate" => ["1989-11-09T18:00:00Z", "1989-11-10T18:00:00Z"], "precip_1h:mm" => [0.0, 0.5]).unwrap();
+        let merged = crate::util::merge_columns(vec![a.clone(), b]).unwrap();
+        assert_eq!(merged.get_column_names(), &["lat", "validdate", "t_2m:C", "precip_1h:mm"]);
+        assert_eq!(merged.height(), 2);
+
+        let shifted = df!("lat" => [47.0, 47.5], "validdate" => ["1989-11-09T18:00:00Z", "1989-11-10T18:00:00Z"], "msl_pressure:hPa" => [1013.0, 1012.0]).unwrap();
+        assert!(crate::util::merge_columns(vec![a, shifted]).is_err());
+        assert!(crate::util::merge_columns(Vec::new()).is_err());
+    }
+
+    #[test]
+    fn check_parse_csv_row() {
+        let header: Vec<String> = ["lat", "lon", "validdate", "t_2m:C", "precip_1h:mm"].iter().map(|h| h.to_string()).collect();
+        let row = crate::util::parse_csv_row(&header, "47.42;9.37;1989-11-09T18:00:00Z;5.1;-", ';', None).unwrap();
+        assert_eq!(row.lat, 47.42);
+        assert_eq!(row.validdate, Utc.ymd(1989, 11, 9).and_hms(18, 0, 0));
+        assert_eq!(row.values, vec![Some(5.1), None]);
+
+        let header: Vec<String> = ["validdate", "t_2m:C"].iter().map(|h| h.to_string()).collect();
+        let row = crate::util::parse_csv_row(&header, "1989-11-09T18:00:00Z;5.1", ';', Some((52.52, 13.405))).unwrap();
+        assert_eq!((row.lat, row.lon), (52.52, 13.405));
+        assert!(crate::util::parse_csv_row(&header, "1989-11-09T18:00:00Z;5.1", ';', None).is_err());
+        assert!(crate::util::parse_csv_row(&header, "1989-11-09T18:00:00Z", ';', Some((52.52, 13.405))).is_err());
+    }
+
+    #[test]
+    fn check_split_param_unit() {
+        use crate::util::split_param_unit;
+        assert_eq!(split_param_unit("t_2m:C"), (String::from("t_2m"), Some(String::from("C"))));
+        assert_eq!(split_param_unit("wind_speed_10m:ms"), (String::from("wind_speed_10m"), Some(String::from("ms"))));
+        assert_eq!(split_param_unit("validdate"), (String::from("validdate"), None));
+
+        let df = df!(
+            "lat" => [47.0],
+            "validdate" => ["1989-11-09T18:00:00Z"],
+            "t_2m:C" => [5.1],
+            "t_2m:F" => [41.2],
+            "wind_speed_10m:ms" => [3.2]
+        ).unwrap();
+        let units = crate::util::parameter_units(&df);
+        assert_eq!(units.len(), 3);
+        assert_eq!(units["t_2m:C"], "C");
+        assert_eq!(units["t_2m:F"], "F");
+        assert_eq!(units["wind_speed_10m:ms"], "ms");
+    }
+
+    #[tokio::test]
+    async fn check_write_bytes_leaves_no_partial_file() {
+        let dir = std::env::temp_dir().join(format!("meteomatics_write_bytes_{}", std::process::id()));
+        let file_name = dir.join("grid.png").to_str().unwrap().to_string();
+        crate::util::create_path(&file_name).await.unwrap();
+
+        crate::util::write_bytes(b"png", &file_name).await.unwrap();
+        assert_eq!(fs::read(&file_name).unwrap(), b"png");
+        assert!(!Path::new(&format!("{}.tmp", file_name)).exists());
 
-/// Convert a number of Points to a String according to the Meteomatics API specifications.
-pub async fn points_to_str(coords: &[Point]) -> String {
-    coords.iter().map(|p| format!("{}", p)).collect::<Vec<String>>().join("+")
-}
+        // A non-empty directory cannot be replaced by the file, so the final step of the write fails.
+        let blocked = dir.join("blocked.nc");
+        fs::create_dir_all(blocked.join("content")).unwrap();
+        let blocked = blocked.to_str().unwrap().to_string();
+        assert!(crate::util::write_bytes(b"netcdf", &blocked).await.is_err());
+        assert!(!Path::new(&format!("{}.tmp", blocked)).exists());
+        assert!(Path::new(&blocked).is_dir());
 
-#[cfg(test)]
-mod tests {
+        fs::remove_dir_all(dir).unwrap();
+    }
 
-    use chrono::prelude::*;
-    use chrono::Duration;
-    use crate::location::{Point, BBox};
-    use std::path::Path;
-    use std::fs;
-    use serde_json;
-    use crate::util::{UStatsResponse, TimeSeries};
+    #[tokio::test]
+    async fn check_http_error() {
+        use crate::errors::ConnectorError;
+        let url = url::Url::parse("https://api.meteomatics.com/user_stats_json").unwrap();
+        let response = |status: reqwest::StatusCode| crate::cache::response_from_parts(
+            url.clone(), status, reqwest::header::HeaderMap::new(), b"Unauthorized access".to_vec()
+        );
+
+        let error = crate::util::http_error(response(reqwest::StatusCode::UNAUTHORIZED)).await;
+        match &error {
+            ConnectorError::AuthError(msg) => {
+                assert!(msg.starts_with("401 Unauthorized"));
+                assert!(msg.contains("username and password"));
+            }
+            other => panic!("unexpected error: {:?}", other),
+        }
+        assert!(!error.is_retryable());
+        assert!(matches!(
+            crate::util::http_error(response(reqwest::StatusCode::FORBIDDEN)).await,
+            ConnectorError::AuthError(_)
+        ));
+        assert!(matches!(
+            crate::util::http_error(response(reqwest::StatusCode::NOT_FOUND)).await,
+            ConnectorError::HttpError(_, _, reqwest::StatusCode::NOT_FOUND)
+        ));
+    }
+
+    #[test]
+    fn check_fill_to_nan() {
+        let values = [5.1, -999.0, 4.9, -999.0];
+        let masked = crate::util::fill_to_nan(&values, Some(-999.0));
+        assert_eq!(masked[0], 5.1);
+        assert!(masked[1].is_nan());
+        assert_eq!(masked[2], 4.9);
+        assert!(masked[3].is_nan());
+        assert_eq!(crate::util::fill_to_nan(&values, None), values.to_vec());
+    }
+
+    #[cfg(feature = "netcdf")]
+    #[test]
+    fn check_netcdf_fill_to_nan() {
+        // A 2x3 lat/lon grid of t_2m with _FillValue -999 at two grid points.
+        let file_name = "tests/netcdfs/t_2m_fill_value.nc";
+        let values = crate::util::netcdf_fill_to_nan(file_name, "t_2m").unwrap();
+        assert_eq!(values.len(), 6);
+        assert_eq!(values[0], 1.5);
+        assert!(values[1].is_nan());
+        assert_eq!(values[2], 3.25);
+        assert!(values[3].is_nan());
+        assert_eq!(&values[4..], &[5.0, 6.5]);
+
+        // The coordinates have no fill value and are returned unchanged.
+        assert_eq!(crate::util::netcdf_fill_to_nan(file_name, "lat").unwrap(), vec![47.0, 48.0]);
+        assert!(crate::util::netcdf_fill_to_nan(file_name, "precip_1h").is_err());
+        assert!(crate::util::netcdf_fill_to_nan("tests/netcdfs/missing.nc", "t_2m").is_err());
+    }
+
+    #[test]
+    fn check_time_series_chunks() {
+        let start = Utc.ymd(1989, 11, 9).and_hms(0, 0, 0);
+        let time_series = TimeSeries { start, end: start + Duration::hours(47), timedelta: Some(Duration::hours(1)) };
+        let chunks = time_series.chunks(Duration::days(1));
+        assert_eq!(chunks.len(), 2);
+        assert_eq!((chunks[0].start, chunks[0].end), (start, start + Duration::hours(23)));
+        assert_eq!((chunks[1].start, chunks[1].end), (start + Duration::days(1), start + Duration::hours(47)));
+        let steps: Vec<_> = chunks.iter().flat_map(|c| c.iter().collect::<Vec<_>>()).collect();
+        assert_eq!(steps, time_series.iter().collect::<Vec<_>>());
 
+        // A chunk shorter than the timedelta still contains one point in time.
+        assert_eq!(time_series.chunks(Duration::minutes(30)).len(), 48);
+    }
 
     #[tokio::test]
     async fn check_path_creation_nonexistent() {
@@ -459,10 +2895,631 @@ mod tests {
         crate::util::create_path(&file_name).await.unwrap();
         let dir: &Path = Path::new(&file_name).parent().unwrap();
         let check: bool = dir.is_dir();
-        assert_eq!(check, true);
+        assert!(check);
         fs::remove_dir_all(dir).unwrap();
     }
 
+    #[tokio::test]
+    async fn check_write_df_csv() {
+        let file_name = "tests/csv/my_time_series.csv";
+        let df_in = df!(
+            "lat" => &[52.52, 52.52],
+            "lon" => &[13.405, 13.405],
+            "validdate" => &["1989-11-09T18:00:00Z", "1989-11-10T06:00:00Z"],
+            "t_2m:C" => &[6.8, 1.4]
+        ).unwrap();
+        crate::util::write_df_csv(&df_in, file_name).await.unwrap();
+
+        let df_out = CsvReader::from_path(file_name).unwrap()
+            .with_delimiter(b';')
+            .has_header(true)
+            .finish()
+            .unwrap();
+        assert!(df_in.frame_equal(&df_out));
+
+        fs::remove_dir_all(Path::new(file_name).parent().unwrap()).unwrap();
+    }
+
+    #[tokio::test]
+    async fn check_write_df_ndjson() {
+        let df = df!(
+            "lat" => &[52.52, 52.52],
+            "lon" => &[13.405, 13.405],
+            "validdate" => &["1989-11-09T18:00:00Z", "1989-11-10T06:00:00Z"],
+            "t_2m:C" => &[Some(6.8), None]
+        ).unwrap();
+
+        let mut buffer: Vec<u8> = Vec::new();
+        crate::util::write_df_ndjson(&df, &mut buffer).await.unwrap();
+        let lines: Vec<&str> = std::str::from_utf8(&buffer).unwrap().lines().collect();
+        assert_eq!(lines.len(), df.height());
+
+        for line in &lines {
+            let row: serde_json::Value = serde_json::from_str(line).unwrap();
+            for key in ["lat", "lon", "validdate", "t_2m:C"] {
+                assert!(row.get(key).is_some());
+            }
+        }
+
+        let first: serde_json::Value = serde_json::from_str(lines[0]).unwrap();
+        assert_eq!(first["validdate"], "1989-11-09T18:00:00+00:00");
+        assert_eq!(first["t_2m:C"], 6.8);
+        let second: serde_json::Value = serde_json::from_str(lines[1]).unwrap();
+        assert!(second["t_2m:C"].is_null());
+    }
+
+    #[tokio::test]
+    async fn check_grid_matrix_parsing() {
+        // https://api.meteomatics.com/1989-11-09T18:00:00.000Z/t_2m:C/52.50,13.40_52.40,13.50:0.05,0.05/csv
+        let s = r#"validdate;1989-11-09T18:00:00Z
+        parameter;t_2m:C
+        data;13.4;13.45;13.5
+        52.5;6.8;6.9;6.9
+        52.45;6.8;6.8;6.9
+        52.4;6.8;6.9;6.9
+        "#;
+
+        let grid = crate::util::parse_grid_csv_to_matrix(s).await.unwrap();
+        assert_eq!(grid.lats, vec![52.5, 52.45, 52.4]);
+        assert_eq!(grid.lons, vec![13.4, 13.45, 13.5]);
+        assert_eq!(grid.values.len(), 3);
+        assert_eq!(grid.values[0], vec![6.8, 6.9, 6.9]);
+        assert_eq!(grid.values[1], vec![6.8, 6.8, 6.9]);
+        assert_eq!(grid.values[2], vec![6.8, 6.9, 6.9]);
+
+        // Rows with a missing value are rejected
+        let s = "data;13.4;13.45\n52.5;6.8\n";
+        assert!(crate::util::parse_grid_csv_to_matrix(s).await.is_err());
+    }
+
+    #[test]
+    fn check_locations_to_str() {
+        use crate::location::Location;
+        let locations = vec![
+            Location::Coord(Point { lat: 47.423938, lon: 9.372858 }),
+            Location::Postal(String::from("postal_CH8000")),
+            Location::Station(String::from("metar_LSZH")),
+        ];
+        assert_eq!(crate::util::locations_to_str(&locations), "47.423938,9.372858+postal_CH8000+metar_LSZH");
+    }
+
+    #[test]
+    fn check_format_route_stops() {
+        use crate::location::{RouteLocation, RouteStop};
+        let start = Utc.ymd(2021, 5, 25).and_hms(12, 0, 0);
+        let stops = vec![
+            RouteStop { when: start, location: RouteLocation::Point(Point { lat: 47.423938, lon: 9.372858 }) },
+            RouteStop { when: start + Duration::hours(1), location: RouteLocation::Postal(String::from("postal_CH8000")) },
+        ];
+        let (dates_str, points_str) = crate::util::format_route_stops(&stops, chrono::SecondsFormat::AutoSi);
+        assert_eq!(dates_str, "2021-05-25T12:00:00+00:00,2021-05-25T13:00:00+00:00");
+        assert_eq!(points_str, "47.423938,9.372858+postal_CH8000");
+    }
+
+    #[tokio::test]
+    async fn check_lightning_parsing() {
+        // https://api.meteomatics.com/get_lightning_list?time_range=2022-05-20T10:00:00Z--2022-05-21T10:00:00Z&bounding_box=47.8084648,5.9559113_45.8179716,10.4922941&format=csv
+        let s = r#"stroke_time:sql;stroke_lat:d;stroke_lon:d;stroke_current:kA
+        2022-05-20T10:00:01.300Z;46.95;7.44;-12.3
+        2022-05-20T10:07:42Z;47.01;8.12;5.1
+        "#;
+
+        let strokes = crate::util::parse_lightning_csv(s).await.unwrap();
+        assert_eq!(strokes, vec![
+            crate::util::LightningStroke {
+                time: Utc.ymd(2022, 5, 20).and_hms_milli(10, 0, 1, 300),
+                lat: 46.95,
+                lon: 7.44,
+            },
+            crate::util::LightningStroke {
+                time: Utc.ymd(2022, 5, 20).and_hms(10, 7, 42),
+                lat: 47.01,
+                lon: 8.12,
+            },
+        ]);
+
+        // No strokes in the time range
+        assert!(crate::util::parse_lightning_csv("stroke_time:sql;stroke_lat:d;stroke_lon:d\n").await.unwrap().is_empty());
+
+        // Missing columns and malformed rows are rejected
+        assert!(crate::util::parse_lightning_csv("validdate;lat\n").await.is_err());
+        let s = "stroke_time:sql;stroke_lat:d;stroke_lon:d\n2022-05-20T10:00:01Z;46.95\n";
+        assert!(crate::util::parse_lightning_csv(s).await.is_err());
+    }
+
+    #[cfg(feature = "ndarray")]
+    #[test]
+    fn check_grid_df_to_ndarray() {
+        // https://api.meteomatics.com/1989-11-09T18:00:00.000Z--1989-11-10T18:00:00.000Z:PT12H/t_2m:C,precip_1h:mm/52.50,13.40_52.40,13.50:0.05,0.05/csv?model=mix
+        let s = r#"lat;lon;validdate;t_2m:C;precip_1h:mm
+        52.4;13.4;1989-11-09T18:00:00Z;6.8;0.00
+        52.4;13.4;1989-11-10T06:00:00Z;1.5;0.00
+        52.4;13.4;1989-11-10T18:00:00Z;5.4;0.00
+        52.4;13.45;1989-11-09T18:00:00Z;6.9;0.00
+        52.4;13.45;1989-11-10T06:00:00Z;1.5;0.00
+        52.4;13.45;1989-11-10T18:00:00Z;5.3;0.00
+        52.4;13.5;1989-11-09T18:00:00Z;6.9;0.00
+        52.4;13.5;1989-11-10T06:00:00Z;1.5;0.00
+        52.4;13.5;1989-11-10T18:00:00Z;5.3;0.00
+        52.45;13.4;1989-11-09T18:00:00Z;6.8;0.00
+        52.45;13.4;1989-11-10T06:00:00Z;1.4;0.00
+        52.45;13.4;1989-11-10T18:00:00Z;5.4;0.00
+        52.45;13.45;1989-11-09T18:00:00Z;6.8;0.00
+        52.45;13.45;1989-11-10T06:00:00Z;1.4;0.00
+        52.45;13.45;1989-11-10T18:00:00Z;5.3;0.00
+        52.45;13.5;1989-11-09T18:00:00Z;6.9;0.00
+        52.45;13.5;1989-11-10T06:00:00Z;1.5;0.00
+        52.45;13.5;1989-11-10T18:00:00Z;5.3;0.00
+        52.5;13.4;1989-11-09T18:00:00Z;6.8;0.05
+        52.5;13.4;1989-11-10T06:00:00Z;1.4;0.00
+        52.5;13.4;1989-11-10T18:00:00Z;5.4;0.00
+        52.5;13.45;1989-11-09T18:00:00Z;6.9;0.00
+        52.5;13.45;1989-11-10T06:00:00Z;1.4;0.00
+        52.5;13.45;1989-11-10T18:00:00Z;5.3;0.00
+        52.5;13.5;1989-11-09T18:00:00Z;6.9;0.00
+        52.5;13.5;1989-11-10T06:00:00Z;1.4;0.00
+        52.5;13.5;1989-11-10T18:00:00Z;5.3;0.00
+        "#;
+        let body: String = s.lines().map(|l| l.trim()).collect::<Vec<&str>>().join("\n");
+        let df = CsvReader::new(std::io::Cursor::new(body))
+            .infer_schema(Some(100))
+            .has_header(true)
+            .with_delimiter(b';')
+            .finish()
+            .unwrap();
+
+        let params = vec![String::from("t_2m:C"), String::from("precip_1h:mm")];
+        let arrays = crate::util::grid_df_to_ndarray(&df, &params).unwrap();
+        let t_2m = &arrays["t_2m:C"];
+        assert_eq!(t_2m.shape(), &[3, 3, 3]);
+        assert_eq!(t_2m[[0, 0, 0]], 6.8);
+        assert_eq!(t_2m[[1, 0, 2]], 1.5);
+        assert_eq!(t_2m[[2, 2, 1]], 5.3);
+        assert_eq!(arrays["precip_1h:mm"][[0, 2, 0]], 0.05);
+
+        // Missing grid points are NaN
+        let partial = df.slice(1, 26);
+        let arrays = crate::util::grid_df_to_ndarray(&partial, &params[..1]).unwrap();
+        assert!(arrays["t_2m:C"][[0, 0, 0]].is_nan());
+
+        assert!(crate::util::grid_df_to_ndarray(&df, &[String::from("wind_speed_10m:ms")]).is_err());
+    }
+
+    #[test]
+    fn check_wind_to_uv() {
+        // Wind from the north, east, south-west and a missing direction
+        let df = df!(
+            "wind_speed_10m:ms" => &[Some(10.0), Some(5.0), Some(2.0f64.sqrt()), Some(3.0)],
+            "wind_dir_10m:d" => &[Some(0.0), Some(90.0), Some(225.0), None]
+        ).unwrap();
+        let df = crate::util::wind_to_uv(&df, "wind_speed_10m:ms", "wind_dir_10m:d").unwrap();
+        let u: Vec<Option<f64>> = df.column("u").unwrap().f64().unwrap().into_iter().collect();
+        let v: Vec<Option<f64>> = df.column("v").unwrap().f64().unwrap().into_iter().collect();
+        let expected = [(0.0, -10.0), (-5.0, 0.0), (1.0, 1.0)];
+        for (i, (eu, ev)) in expected.iter().enumerate() {
+            assert!((u[i].unwrap() - eu).abs() < 1e-9);
+            assert!((v[i].unwrap() - ev).abs() < 1e-9);
+        }
+        assert_eq!((u[3], v[3]), (None, None));
+
+        let text = df!("wind_speed_10m:ms" => &[1.0], "wind_dir_10m:d" => &["N"]).unwrap();
+        assert!(crate::util::wind_to_uv(&text, "wind_speed_10m:ms", "wind_dir_10m:d").is_err());
+        assert!(crate::util::wind_to_uv(&df, "wind_speed_100m:ms", "wind_dir_10m:d").is_err());
+    }
+
+    #[test]
+    fn check_concat_labeled() {
+        let a = df!(
+            "validdate" => &["2022-05-20T00:00:00Z", "2022-05-20T01:00:00Z"],
+            "t_2m:C" => &[11.5, 11.1]
+        ).unwrap();
+        let b = df!(
+            "validdate" => &["2022-05-20T00:00:00Z"],
+            "t_2m:C" => &[9.8]
+        ).unwrap();
+        let df = crate::util::concat_labeled(vec![(String::from("a"), a), (String::from("b"), b)], "station").unwrap();
+        assert_eq!(df.get_column_names(), &["station", "validdate", "t_2m:C"]);
+        assert_eq!(df.height(), 3);
+        let labels: Vec<Option<&str>> = df.column("station").unwrap().utf8().unwrap().into_iter().collect();
+        assert_eq!(labels, vec![Some("a"), Some("a"), Some("b")]);
+
+        assert!(crate::util::concat_labeled(Vec::new(), "station").is_err());
+    }
+
+    #[test]
+    fn check_normalize_location_columns() {
+        let columns = ["location", "lat", "lon", "validdate", "t_2m:C"];
+        let locations = |df: &DataFrame| -> Vec<Option<String>> {
+            df.column("location").unwrap().utf8().unwrap().into_iter().map(|s| s.map(String::from)).collect()
+        };
+
+        // Several points
+        let points = df!(
+            "lat" => &[47.423, 52.52],
+            "lon" => &[9.37, 13.405],
+            "validdate" => &["2022-05-20T00:00:00Z", "2022-05-20T00:00:00Z"],
+            "t_2m:C" => &[11.5, 9.8]
+        ).unwrap();
+        let df = crate::util::normalize_location_columns(points).unwrap();
+        assert_eq!(df.get_column_names(), &columns);
+        assert_eq!(locations(&df), vec![Some(String::from("47.423,9.37")), Some(String::from("52.52,13.405"))]);
+
+        // A single point (coordinates added by df_add_latlon() with integer values in the response)
+        let single = df!(
+            "lat" => &[47i64, 47],
+            "lon" => &[9i64, 9],
+            "validdate" => &["2022-05-20T00:00:00Z", "2022-05-20T01:00:00Z"],
+            "t_2m:C" => &[11.5, 11.1]
+        ).unwrap();
+        let df = crate::util::normalize_location_columns(single).unwrap();
+        assert_eq!(df.get_column_names(), &columns);
+        assert_eq!(df.column("lat").unwrap().dtype(), &DataType::Float64);
+        assert_eq!(locations(&df), vec![Some(String::from("47,9")), Some(String::from("47,9"))]);
+
+        // Postal codes and stations
+        for id in ["postal_CH9000", "wmo_066810"] {
+            let stations = df!(
+                "station_id" => &[id],
+                "validdate" => &["2022-05-20T00:00:00Z"],
+                "t_2m:C" => &[11.5]
+            ).unwrap();
+            let df = crate::util::normalize_location_columns(stations).unwrap();
+            assert_eq!(df.get_column_names(), &columns);
+            assert_eq!(locations(&df), vec![Some(String::from(id))]);
+            assert_eq!(df.column("lat").unwrap().null_count(), 1);
+            assert_eq!(df.column("lon").unwrap().dtype(), &DataType::Float64);
+        }
+
+        // Mixed locations
+        let mixed = df!(
+            "station_id" => &[Some("postal_CH9000"), None],
+            "lat" => &[None, Some(52.52)],
+            "lon" => &[None, Some(13.405)],
+            "validdate" => &["2022-05-20T00:00:00Z", "2022-05-20T00:00:00Z"],
+            "t_2m:C" => &[11.5, 9.8]
+        ).unwrap();
+        let df = crate::util::normalize_location_columns(mixed).unwrap();
+        assert_eq!(df.get_column_names(), &columns);
+        assert_eq!(locations(&df), vec![Some(String::from("postal_CH9000")), Some(String::from("52.52,13.405"))]);
+
+        // Normalizing twice does not change the frame
+        let again = crate::util::normalize_location_columns(df.clone()).unwrap();
+        assert!(again.frame_equal_missing(&df));
+
+        // Without identifying columns
+        let anonymous = df!("validdate" => &["2022-05-20T00:00:00Z"], "t_2m:C" => &[11.5]).unwrap();
+        assert!(crate::util::normalize_location_columns(anonymous).is_err());
+    }
+
+    #[test]
+    fn check_merge_grid_tiles() {
+        // Every grid point of a tile with a value derived from its coordinates
+        let grid = |bbox: &BBox| {
+            let (n_lat, n_lon) = bbox.dimensions();
+            let mut lats = Vec::new();
+            let mut lons = Vec::new();
+            for i in 0..n_lat {
+                for j in 0..n_lon {
+                    lats.push(((bbox.lat_min + i as f64 * bbox.lat_res) * 1e9).round() / 1e9);
+                    lons.push(((bbox.lon_min + j as f64 * bbox.lon_res) * 1e9).round() / 1e9);
+                }
+            }
+            let values: Vec<f64> = lats.iter().zip(&lons).map(|(lat, lon)| lat * 100.0 + lon).collect();
+            let dates = vec!["2022-05-20T00:00:00Z"; lats.len()];
+            df!("lat" => &lats, "lon" => &lons, "validdate" => &dates, "t_2m:C" => &values).unwrap()
+        };
+
+        let bbox = BBox {
+            lat_min: 45.8,
+            lat_max: 47.8,
+            lon_min: 5.9,
+            lon_max: 10.5,
+            lat_res: 0.1,
+            lon_res: 0.2,
+        };
+        let tiles = bbox.tiles((1.0, 2.5));
+        assert_eq!(tiles.len(), 4);
+        let merged = crate::util::merge_grid_tiles(tiles.iter().map(grid).collect()).unwrap();
+        assert_eq!(merged.height(), bbox.cell_count());
+        assert!(crate::util::frames_approx_equal(&merged, &grid(&bbox), 1e-9));
+
+        assert!(crate::util::merge_grid_tiles(Vec::new()).is_err());
+    }
+
+    #[test]
+    fn check_enforce_float_columns() {
+        // Sea-masked parameter: the points on the water have no value
+        let s = "lat;lon;validdate;t_sea_sfc:C\n\
+            54.5;10.0;2022-05-20T00:00:00Z;-\n\
+            54.5;10.5;2022-05-20T00:00:00Z;NaN\n\
+            54.0;10.0;2022-05-20T00:00:00Z;12.5\n";
+        let df = CsvReader::new(std::io::Cursor::new(s))
+            .infer_schema(Some(100))
+            .has_header(true)
+            .with_delimiter(b';')
+            .finish()
+            .unwrap();
+        assert_eq!(df.column("t_sea_sfc:C").unwrap().dtype(), &DataType::Utf8);
+
+        let df = crate::util::enforce_float_columns(df, &["lat", "lon", "t_sea_sfc:C"]).unwrap();
+        let values = df.column("t_sea_sfc:C").unwrap();
+        assert_eq!(values.dtype(), &DataType::Float64);
+        assert_eq!(values.f64().unwrap().into_iter().collect::<Vec<_>>(), vec![None, None, Some(12.5)]);
+        assert_eq!(df.column("lat").unwrap().dtype(), &DataType::Float64);
+
+        assert!(crate::util::enforce_float_columns(df, &["precip_1h:mm"]).is_err());
+    }
+
+    #[test]
+    fn check_historic_window() {
+        let window = crate::util::parse_hist_range("2000-01-01T00:00:00Z--2100-01-01T00:00:00Z").unwrap();
+        assert_eq!(window.0, Utc.ymd(2000, 1, 1).and_hms(0, 0, 0));
+        assert!(crate::util::parse_hist_range("2000-01-01T00:00:00Z").is_err());
+        assert!(crate::util::parse_hist_range("yesterday--today").is_err());
+
+        let start = Utc.ymd(1989, 11, 9).and_hms(18, 0, 0);
+        let before = TimeSeries { start, end: start + Duration::days(1), timedelta: None };
+        assert!(matches!(
+            crate::util::check_historic_window(&before, &window),
+            Err(crate::errors::ConnectorError::LibraryError(msg)) if msg == "requested date before account's historic window"
+        ));
+        let start = Utc.ymd(2022, 5, 20).and_hms(0, 0, 0);
+        let inside = TimeSeries { start, end: start + Duration::days(1), timedelta: None };
+        assert!(crate::util::check_historic_window(&inside, &window).is_ok());
+    }
+
+    #[test]
+    fn check_parse_validdate_column() {
+        let df = df!(
+            "validdate" => &["1989-11-09T18:00:00Z", "1989-11-09T18:00:00.250000Z", "1989-11-09T19:00:00+01:00"],
+            "t_2m:C" => &[6.8, 6.8, 6.8]
+        ).unwrap();
+        let df = crate::util::parse_validdate_column(df).unwrap();
+        let validdate = df.column("validdate").unwrap();
+        assert_eq!(validdate.dtype(), &DataType::Datetime(TimeUnit::Microseconds, None));
+        let micros = validdate.cast(&DataType::Int64).unwrap();
+        let micros: Vec<Option<i64>> = micros.i64().unwrap().into_iter().collect();
+        let expected = Utc.ymd(1989, 11, 9).and_hms(18, 0, 0).timestamp() * 1_000_000;
+        assert_eq!(micros, vec![Some(expected), Some(expected + 250_000), Some(expected)]);
+
+        // Dates outside of the range of nanosecond timestamps (1677 to 2262), e.g. of climate queries.
+        let distant = df!("validdate" => &["2300-01-01T00:00:00.500000Z", "1600-01-01T00:00:00Z"]).unwrap();
+        let distant = crate::util::parse_validdate_column(distant).unwrap();
+        let micros = distant.column("validdate").unwrap().cast(&DataType::Int64).unwrap();
+        let micros: Vec<Option<i64>> = micros.i64().unwrap().into_iter().collect();
+        assert_eq!(micros, vec![
+            Some(Utc.ymd(2300, 1, 1).and_hms(0, 0, 0).timestamp() * 1_000_000 + 500_000),
+            Some(Utc.ymd(1600, 1, 1).and_hms(0, 0, 0).timestamp() * 1_000_000),
+        ]);
+
+        let invalid = df!("validdate" => &["yesterday"]).unwrap();
+        assert!(crate::util::parse_validdate_column(invalid).is_err());
+    }
+
+    #[test]
+    fn check_sanitize_file_name() {
+        assert_eq!(crate::util::sanitize_file_name("t_2m:C"), "t_2m_C");
+        assert_eq!(crate::util::sanitize_file_name("wind_speed_u_10m:ms"), "wind_speed_u_10m_ms");
+        assert_eq!(crate::util::sanitize_file_name("precip_1h:mm/s"), "precip_1h_mm_s");
+    }
+
+    #[test]
+    fn check_frames_approx_equal() {
+        let df = df!(
+            "validdate" => &["2022-05-20T00:00:00Z", "2022-05-20T01:00:00Z"],
+            "t_2m:C" => &[Some(11.5), None],
+            "count" => &[1i64, 2]
+        ).unwrap();
+        let close = df!(
+            "validdate" => &["2022-05-20T00:00:00Z", "2022-05-20T01:00:00Z"],
+            "t_2m:C" => &[Some(11.5 + 1e-9), None],
+            "count" => &[1i64, 2]
+        ).unwrap();
+        let far = df!(
+            "validdate" => &["2022-05-20T00:00:00Z", "2022-05-20T01:00:00Z"],
+            "t_2m:C" => &[Some(11.6), None],
+            "count" => &[1i64, 2]
+        ).unwrap();
+        assert!(crate::util::frames_approx_equal(&df, &close, 1e-6));
+        assert!(!crate::util::frames_approx_equal(&df, &far, 1e-6));
+
+        // String columns, missing values and the column names have to match exactly
+        let other_date = df!(
+            "validdate" => &["2022-05-20T00:00:00Z", "2022-05-20T02:00:00Z"],
+            "t_2m:C" => &[Some(11.5), None],
+            "count" => &[1i64, 2]
+        ).unwrap();
+        assert!(!crate::util::frames_approx_equal(&df, &other_date, 1e-6));
+        let filled = df!(
+            "validdate" => &["2022-05-20T00:00:00Z", "2022-05-20T01:00:00Z"],
+            "t_2m:C" => &[Some(11.5), Some(10.0)],
+            "count" => &[1i64, 2]
+        ).unwrap();
+        assert!(!crate::util::frames_approx_equal(&df, &filled, 1e-6));
+        assert!(!crate::util::frames_approx_equal(&df, &df.drop("count").unwrap(), 1e-6));
+    }
+
+    #[test]
+    fn check_decode_body() {
+        // "Zürich" in Latin-1
+        let latin1 = b"station;t_2m:C\nZ\xfcrich;12.5\n";
+        assert_eq!(
+            crate::util::decode_body(latin1, Some("text/csv; charset=iso-8859-1")).unwrap(),
+            "station;t_2m:C\nZürich;12.5\n"
+        );
+        // Without a charset the body has to be UTF-8
+        assert!(crate::util::decode_body(latin1, Some("text/csv")).is_err());
+        assert!(crate::util::decode_body(latin1, None).is_err());
+        assert_eq!(
+            crate::util::decode_body("Zürich".as_bytes(), Some("text/csv; charset=\"utf-8\"")).unwrap(),
+            "Zürich"
+        );
+        assert!(crate::util::decode_body(b"", Some("text/csv; charset=klingon")).is_err());
+    }
+
+    #[tokio::test]
+    async fn check_html_response_rejected() {
+        let response = |content_type: &str, body: &'static str| {
+            let response = http::Response::builder()
+                .header("content-type", content_type)
+                .body(body)
+                .unwrap();
+            reqwest::Response::from(response)
+        };
+        let html = "<!DOCTYPE html><html><body>Access denied</body></html>";
+
+        // HTML error page delivered with status 200
+        let err = crate::util::parse_response_to_df(response("text/html; charset=utf-8", html)).await.unwrap_err();
+        assert!(err.to_string().contains("unexpected non-CSV response"));
+        assert!(err.to_string().contains("Access denied"));
+
+        // HTML body with a misleading content type
+        assert!(crate::util::parse_response_to_df(response("text/csv", html)).await.is_err());
+        assert!(crate::util::parse_grid_response_to_matrix(response("text/html", html)).await.is_err());
+
+        // CSV is accepted
+        let df = crate::util::parse_response_to_df(
+            response("text/csv", "validdate;t_2m:C\n2022-05-17T12:00:00Z;12.5\n")
+        ).await.unwrap();
+        assert_eq!(df.height(), 1);
+    }
+
+    #[test]
+    fn check_retry_after() {
+        let response = |value: Option<&str>| {
+            let mut builder = http::Response::builder().status(429);
+            if let Some(value) = value {
+                builder = builder.header("retry-after", value);
+            }
+            reqwest::Response::from(builder.body("").unwrap())
+        };
+
+        assert_eq!(
+            crate::util::parse_retry_after(&response(Some("120"))),
+            Some(std::time::Duration::from_secs(120))
+        );
+        // Dates in the past do not require waiting
+        assert_eq!(
+            crate::util::parse_retry_after(&response(Some("Wed, 21 Oct 2015 07:28:00 GMT"))),
+            Some(std::time::Duration::from_secs(0))
+        );
+        assert_eq!(crate::util::parse_retry_after(&response(Some("soon"))), None);
+        assert_eq!(crate::util::parse_retry_after(&response(None)), None);
+    }
+
+    #[test]
+    fn check_to_wide_by_station() {
+        // Reference data for two postal codes from the python connector
+        let df = df!(
+            "station_id" => &["postal_CH8000", "postal_CH8000", "postal_CH8000", "postal_CH9000", "postal_CH9000", "postal_CH9000"],
+            "validdate" => &["1989-11-09T18:00:00Z", "1989-11-10T06:00:00Z", "1989-11-10T18:00:00Z", "1989-11-09T18:00:00Z", "1989-11-10T06:00:00Z", "1989-11-10T18:00:00Z"],
+            "t_2m:C" => &[5.8, 3.1, 5.5, 4.6, 0.9, 3.1],
+            "precip_1h:mm" => &[0.0, 0.0, 0.0, 0.0, 0.0, 0.0]
+        ).unwrap();
+
+        let wide = crate::util::to_wide_by_station(&df, "t_2m:C").unwrap();
+        assert_eq!(wide.get_column_names(), &["validdate", "postal_CH8000", "postal_CH9000"]);
+        assert_eq!(wide.height(), 3);
+        let ch8000: Vec<Option<f64>> = wide.column("postal_CH8000").unwrap().f64().unwrap().into_iter().collect();
+        assert_eq!(ch8000, vec![Some(5.8), Some(3.1), Some(5.5)]);
+        let ch9000: Vec<Option<f64>> = wide.column("postal_CH9000").unwrap().f64().unwrap().into_iter().collect();
+        assert_eq!(ch9000, vec![Some(4.6), Some(0.9), Some(3.1)]);
+
+        // Unknown value columns are reported
+        assert!(crate::util::to_wide_by_station(&df, "wind_speed_10m:ms").is_err());
+    }
+
+    #[test]
+    fn check_correlate() {
+        // Perfectly correlated at the first location, perfectly anti-correlated at the second and a
+        // single value at the third location.
+        let df = df!(
+            "lat" => &[47.42, 47.42, 47.42, 46.95, 46.95, 46.95, 46.0],
+            "lon" => &[9.37, 9.37, 9.37, 7.43, 7.43, 7.43, 7.0],
+            "t_2m:C" => &[10.0, 12.0, 15.0, 10.0, 12.0, 15.0, 8.0],
+            "dew_point_2m:C" => &[5.0, 9.0, 15.0, 3.0, 2.0, 0.5, 2.0]
+        ).unwrap();
+
+        let corr = crate::util::correlate(&df, "t_2m:C", "dew_point_2m:C", &["lat", "lon"]).unwrap();
+        assert_eq!(corr.get_column_names(), &["lat", "lon", "correlation"]);
+        let values: Vec<Option<f64>> = corr.column("correlation").unwrap().f64().unwrap().into_iter().collect();
+        assert_eq!(values.len(), 3);
+        assert!((values[0].unwrap() - 1.0).abs() < 1e-12);
+        assert!((values[1].unwrap() + 1.0).abs() < 1e-12);
+        assert_eq!(values[2], None);
+
+        // Without groups a single correlation over all rows is computed
+        let corr = crate::util::correlate(&df, "t_2m:C", "t_2m:C", &[]).unwrap();
+        assert!((corr.column("correlation").unwrap().f64().unwrap().get(0).unwrap() - 1.0).abs() < 1e-12);
+
+        // Missing columns are reported
+        assert!(crate::util::correlate(&df, "t_2m:C", "precip_1h:mm", &["lat"]).is_err());
+    }
+
+    #[test]
+    fn check_join_metadata() {
+        let result = df!(
+            "lat" => &[47.42, 47.42, 46.95],
+            "lon" => &[9.37, 9.37, 7.43],
+            "validdate" => &["2021-05-25T12:00:00Z", "2021-05-25T13:00:00Z", "2021-05-25T12:00:00Z"],
+            "t_2m:C" => &[11.5, 13.2, 12.8]
+        ).unwrap();
+        let metadata = df!(
+            "lat" => &[47.42, 46.95],
+            "lon" => &[9.37, 7.43],
+            "name" => &["St. Gallen", "Bern"],
+            "elevation" => &[675, 540]
+        ).unwrap();
+
+        let df = crate::util::join_metadata(&result, &metadata, &["lat", "lon"]).unwrap();
+        assert_eq!(df.height(), 3);
+        assert_eq!(
+            df.get_column_names(),
+            &["lat", "lon", "validdate", "t_2m:C", "name", "elevation"]
+        );
+        let names: Vec<Option<&str>> = df.column("name").unwrap().utf8().unwrap().into_iter().collect();
+        assert_eq!(names, vec![Some("St. Gallen"), Some("St. Gallen"), Some("Bern")]);
+        let elevations: Vec<Option<i32>> = df.column("elevation").unwrap().i32().unwrap().into_iter().collect();
+        assert_eq!(elevations, vec![Some(675), Some(675), Some(540)]);
+
+        // Missing keys are reported
+        assert!(crate::util::join_metadata(&result, &metadata, &["station_id"]).is_err());
+    }
+
+    #[test]
+    fn check_simplified_wbgt() {
+        // Reference values computed with the formula of the Australian Bureau of Meteorology
+        assert!((crate::util::simplified_wbgt(30.0, 50.0) - 29.259).abs() < 1e-3);
+        assert!((crate::util::simplified_wbgt(20.0, 60.0) - 20.779).abs() < 1e-3);
+        assert!((crate::util::simplified_wbgt(35.0, 40.0) - 32.591).abs() < 1e-3);
+
+        let df = df!(
+            "t_2m:C" => &[Some(30.0), Some(20.0), None],
+            "relative_humidity_2m:p" => &[50.0, 60.0, 70.0]
+        ).unwrap();
+        let df = crate::util::df_add_wbgt(df, "t_2m:C", "relative_humidity_2m:p").unwrap();
+        let wbgt: Vec<Option<f64>> = df.column("wbgt:C").unwrap().f64().unwrap().into_iter().collect();
+        assert!((wbgt[0].unwrap() - 29.259).abs() < 1e-3);
+        assert!((wbgt[1].unwrap() - 20.779).abs() < 1e-3);
+        assert!(wbgt[2].is_none());
+    }
+
+    #[test]
+    fn check_time_series_iter() {
+        let start_date = Utc.ymd(2022, 5, 17).and_hms(12, 00, 00);
+        let time_series = TimeSeries{
+            start: start_date,
+            end: start_date + Duration::hours(12),
+            timedelta: Option::from(Duration::hours(12))
+        };
+        let dates: Vec<_> = time_series.iter().collect();
+        assert_eq!(dates, vec![start_date, start_date + Duration::hours(12)]);
+
+        // Without timedelta only the start is returned
+        let time_series = TimeSeries{ timedelta: None, ..time_series };
+        assert_eq!(time_series.iter().collect::<Vec<_>>(), vec![start_date]);
+    }
+
     #[tokio::test]
     // checks if the location specifier is correctly created
     async fn check_locations_string() {
@@ -490,7 +3547,7 @@ mod tests {
         let coord_str = crate::util::points_to_str(&coords).await;
 
         let query_s = crate::util::build_ts_query_specs(
-            &time_series, &parameters, &coord_str, &None, &String::from("csv")
+            &time_series, &parameters, &coord_str, &None, Format::Csv
         ).await;
         assert_eq!(
             "2022-05-17T12:00:00+00:00--2022-05-18T12:00:00+00:00:PT3600S/t_2m:C/52.520551,13.461804/csv", 
@@ -506,7 +3563,7 @@ mod tests {
         };
 
         let query_ms = crate::util::build_ts_query_specs(
-            &time_series, &parameters, &coord_str, &None, &String::from("csv")
+            &time_series, &parameters, &coord_str, &None, Format::Csv
         ).await;
         assert_eq!(
             "2022-05-17T12:00:00.453829+00:00--2022-05-18T12:00:00.453829+00:00:PT3600S/t_2m:C/52.520551,13.461804/csv", 
@@ -522,12 +3579,90 @@ mod tests {
         };
 
         let query_ns = crate::util::build_ts_query_specs(
-            &time_series, &parameters, &coord_str, &None, &String::from("csv")
+            &time_series, &parameters, &coord_str, &None, Format::Csv
         ).await;
         assert_eq!(
             "2022-05-17T12:00:00.453829123+00:00--2022-05-18T12:00:00.453829123+00:00:PT3600S/t_2m:C/52.520551,13.461804/csv", 
             query_ns
         );
+
+        // nanoseconds truncated to whole seconds
+        let query_trunc = crate::util::build_ts_query_specs_with_precision(
+            &time_series, &parameters, &coord_str, &None, Format::Csv, chrono::SecondsFormat::Secs
+        ).await.unwrap();
+        assert_eq!(
+            "2022-05-17T12:00:00+00:00--2022-05-18T12:00:00+00:00:PT3600S/t_2m:C/52.520551,13.461804/csv",
+            query_trunc
+        );
+        let query_grid = crate::util::build_grid_query_specs_with_precision(
+            &start_date, &parameters[0], &coord_str, &None, Format::Csv, chrono::SecondsFormat::Millis
+        ).await;
+        assert_eq!("2022-05-17T12:00:00.453+00:00/t_2m:C/52.520551,13.461804/csv", query_grid);
+        let query_grid_ts = crate::util::build_grid_ts_query_specs_with_precision(
+            &time_series, &parameters[0], &coord_str, Format::Csv, &None, chrono::SecondsFormat::Secs
+        ).await.unwrap();
+        assert_eq!(
+            "2022-05-17T12:00:00+00:00--2022-05-18T12:00:00+00:00:PT3600S/t_2m:C/52.520551,13.461804/csv",
+            query_grid_ts
+        );
+        let query_lightning = crate::util::build_grid_ts_lightning_query_specs_with_precision(
+            &time_series, &coord_str, chrono::SecondsFormat::Secs
+        ).await;
+        assert_eq!(
+            "get_lightning_list?time_range=2022-05-17T12:00:00+00:00--2022-05-18T12:00:00+00:00\
+            &bounding_box=52.520551,13.461804&format=csv",
+            query_lightning
+        );
+
+        // A time series without a timedelta cannot be rendered
+        let no_step = TimeSeries { start: start_date, end: start_date, timedelta: None };
+        assert!(no_step.to_string_with_precision(chrono::SecondsFormat::Secs).is_err());
+        assert!(crate::util::build_ts_query_specs_with_precision(
+            &no_step, &parameters, &coord_str, &None, Format::Csv, chrono::SecondsFormat::Secs
+        ).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn check_line_query_specs_string() {
+        let start_date = Utc.ymd(2022, 5, 17).and_hms(12, 00, 00);
+        let time_series = TimeSeries{
+            start: start_date,
+            end: start_date,
+            timedelta: Option::from(Duration::hours(1))
+        };
+        let parameters: Vec<String> = vec![String::from("t_2m:C")];
+        let line_str = crate::location::line_to_str(
+            &Point { lat: 50.0, lon: 10.0 }, &Point { lat: 50.0, lon: 20.0 }, 100
+        );
+
+        let query_s = crate::util::build_ts_query_specs(
+            &time_series, &parameters, &line_str, &None, Format::Csv
+        ).await;
+        assert_eq!(
+            "2022-05-17T12:00:00+00:00--2022-05-17T12:00:00+00:00:PT3600S/t_2m:C/50,10_50,20:100/csv",
+            query_s
+        );
+    }
+
+    #[tokio::test]
+    async fn check_dates_query_specs_string() {
+        let dates = vec![
+            Utc.ymd(2022, 5, 17).and_hms(12, 00, 00),
+            Utc.ymd(2022, 5, 19).and_hms(12, 00, 00),
+            Utc.ymd(2022, 6, 1).and_hms(12, 00, 00),
+        ];
+        let parameters: Vec<String> = vec![String::from("t_2m:C"), String::from("precip_1h:mm")];
+        let coords = vec![Point { lat: 52.520551, lon: 13.461804}];
+        let coord_str = crate::util::points_to_str(&coords).await;
+        let optionals = Some(vec![String::from("model=mix")]);
+
+        let query_s = crate::util::build_dates_query_specs(
+            &dates, &parameters, &coord_str, &optionals, Format::Csv, chrono::SecondsFormat::AutoSi
+        ).await;
+        assert_eq!(
+            "2022-05-17T12:00:00+00:00,2022-05-19T12:00:00+00:00,2022-06-01T12:00:00+00:00/t_2m:C,precip_1h:mm/52.520551,13.461804/csv?model=mix",
+            query_s
+        );
     }
 
     #[tokio::test]
@@ -583,10 +3718,332 @@ mod tests {
         // Check if the area request option was correctly deserialized.
         assert!(json.stats.area);
 
+        // Check if the historic request option is parsed into a time range.
+        let window = json.stats.hist_range().unwrap();
+        assert_eq!(window, (Utc.ymd(1900, 1, 1).and_hms(0, 0, 0), Utc.ymd(2100, 1, 1).and_hms(0, 0, 0)));
+
         // Check if the model set was correctly deserialized.
         assert_eq!(json.stats.models[0], "all_minus_euro1k");
 
         // Check if the contact was correctly deserialized.
         assert_eq!(json.stats.contact[0], "rustythecrab@meteomatics.com");
+
+        // Check the effective limits (0 means not set).
+        assert_eq!(json.stats.total.effective(), None);
+        assert_eq!(json.stats.since_60s.effective(), Some(6000));
+        assert_eq!(json.stats.parallel.effective(), Some(20));
+
+        // Check the remaining requests (None for unlimited).
+        assert_eq!(json.stats.total.remaining_soft(), None);
+        assert_eq!(json.stats.total.remaining_hard(), None);
+        assert_eq!(json.stats.since_midnight.remaining_soft(), Some(99915));
+        assert_eq!(json.stats.since_midnight.remaining_hard(), None);
+        assert_eq!(json.stats.since_60s.remaining_hard(), Some(6000));
+
+        // Check the warning threshold; the unlimited total of 4280 requests is ignored.
+        let mut stats = json.stats;
+        assert!(!stats.is_near_limit(0.9));
+        assert!(stats.is_near_limit(0.0));
+        stats.since_60s.used = 5400;
+        assert!(stats.is_near_limit(0.9));
+        assert!(!stats.is_near_limit(0.95));
+    }
+
+    #[test]
+    fn check_limit_remaining() {
+        use crate::util::Limit;
+        let limit = Limit { used: 5, soft_lim: 20, hard_lim: 500 };
+        assert_eq!(limit.remaining_soft(), Some(15));
+        assert_eq!(limit.remaining_hard(), Some(495));
+
+        // Exceeded limits saturate at 0.
+        let exceeded = Limit { used: 25, soft_lim: 20, hard_lim: 500 };
+        assert_eq!(exceeded.remaining_soft(), Some(0));
+        assert_eq!(exceeded.remaining_hard(), Some(475));
+
+        // A limit of 0 is not set.
+        let unlimited = Limit { used: 25, soft_lim: 0, hard_lim: 0 };
+        assert_eq!(unlimited.remaining_soft(), None);
+        assert_eq!(unlimited.remaining_hard(), None);
+        let hard_only = Limit { used: 25, soft_lim: 0, hard_lim: 30 };
+        assert_eq!(hard_only.remaining_soft(), None);
+        assert_eq!(hard_only.remaining_hard(), Some(5));
+    }
+
+    #[test]
+    fn check_user_stats_near_limit() {
+        use crate::util::{Limit, UserStats};
+        let unlimited = || Limit { used: 1000, soft_lim: 0, hard_lim: 0 };
+        let mut stats = UserStats {
+            username: String::from("rustythecrab"),
+            total: unlimited(),
+            since_midnight: unlimited(),
+            since_0: unlimited(),
+            since_60s: unlimited(),
+            parallel: Limit { used: 20, soft_lim: 0, hard_lim: 20 },
+            hist: String::new(),
+            area: true,
+            models: vec![],
+            error: String::new(),
+            contact: vec![],
+        };
+        // Unlimited quotas are never near their limit and the parallel requests are not counted.
+        assert!(!stats.is_near_limit(0.0));
+
+        stats.since_midnight = Limit { used: 89, soft_lim: 100, hard_lim: 0 };
+        assert!(!stats.is_near_limit(0.9));
+        stats.since_midnight.used = 90;
+        assert!(stats.is_near_limit(0.9));
+
+        // The stricter of the soft and the hard limit counts.
+        stats.since_midnight = Limit { used: 45, soft_lim: 100, hard_lim: 50 };
+        assert!(stats.is_near_limit(0.9));
+        assert!(!stats.is_near_limit(0.95));
+    }
+
+    #[tokio::test]
+    async fn check_ensemble_size() {
+        let s = "validdate;t_2m:C-m0;t_2m:C-m1;t_2m:C-m2\n2022-05-17T12:00:00Z;12.1;11.8;12.6\n";
+        let df = CsvReader::new(std::io::Cursor::new(s))
+            .with_delimiter(b';')
+            .has_header(true)
+            .finish()
+            .unwrap();
+        assert_eq!(crate::util::extract_ensemble_size(&df).await, 3);
+    }
+
+    #[test]
+    fn check_ensemble_to_long() {
+        let s = "validdate;t_2m:C-m0;t_2m:C-m1;t_2m:C-m2\n2022-05-17T12:00:00Z;12.1;11.8;12.6\n2022-05-17T13:00:00Z;13.0;12.2;13.4\n";
+        let df = CsvReader::new(std::io::Cursor::new(s))
+            .with_delimiter(b';')
+            .has_header(true)
+            .finish()
+            .unwrap();
+
+        let df_long = crate::util::df_ensemble_to_long(&df, "t_2m:C", "m0").unwrap();
+        assert_eq!(df_long.get_column_names(), &["validdate", "member", "t_2m:C"]);
+        assert_eq!(df_long.height(), 6);
+        let members: Vec<Option<&str>> = df_long.column("member").unwrap().utf8().unwrap().into_iter().collect();
+        assert_eq!(members, vec![Some("m0"), Some("m0"), Some("m1"), Some("m1"), Some("m2"), Some("m2")]);
+        let values: Vec<Option<f64>> = df_long.column("t_2m:C").unwrap().f64().unwrap().into_iter().collect();
+        assert_eq!(values[2..4], [Some(11.8), Some(12.2)]);
+
+        // A single selected member has no suffix
+        let df = df!("validdate" => &["2022-05-17T12:00:00Z"], "t_2m:C" => &[12.4]).unwrap();
+        let df_long = crate::util::df_ensemble_to_long(&df, "t_2m:C", "q0.5").unwrap();
+        assert_eq!(df_long.column("member").unwrap().utf8().unwrap().get(0), Some("q0.5"));
+
+        // Unknown parameters are reported
+        assert!(crate::util::df_ensemble_to_long(&df, "precip_1h:mm", "m0").is_err());
+    }
+
+    #[test]
+    fn check_lightning_columns() {
+        let df = df!(
+            "stroke_time:sql" => &["2022-05-20T10:00:01Z", "2022-05-20T10:07:42Z"],
+            "stroke_lat:d" => &[46.95, 47.01],
+            "stroke_lon:d" => &[7.44, 8.12]
+        ).unwrap();
+
+        // Default: the names of the other query results
+        let mut df_default = df.clone();
+        crate::util::rename_lightning_columns(&mut df_default, &crate::util::LightningColumns::default()).unwrap();
+        assert_eq!(df_default.get_column_names(), &["validdate", "lat", "lon"]);
+
+        // Keep the names of the API
+        let mut df_original = df.clone();
+        crate::util::rename_lightning_columns(&mut df_original, &crate::util::LightningColumns::original()).unwrap();
+        assert_eq!(df_original.get_column_names(), &["stroke_time:sql", "stroke_lat:d", "stroke_lon:d"]);
+
+        // Custom names
+        let mut df_custom = df;
+        let names = crate::util::LightningColumns {
+            validdate: String::from("time"),
+            ..crate::util::LightningColumns::default()
+        };
+        crate::util::rename_lightning_columns(&mut df_custom, &names).unwrap();
+        assert_eq!(df_custom.get_column_names(), &["time", "lat", "lon"]);
+    }
+
+    #[test]
+    fn check_threshold_crossings() {
+        // The first location drops below and rises above 0°C again, the second never crosses.
+        let df = df!(
+            "lat" => &[47.42, 47.42, 47.42, 47.42, 47.42, 46.95, 46.95],
+            "lon" => &[9.37, 9.37, 9.37, 9.37, 9.37, 7.43, 7.43],
+            "validdate" => &["t0", "t1", "t2", "t3", "t4", "t0", "t1"],
+            "t_2m:C" => &[Some(2.1), Some(-0.5), None, Some(-1.2), Some(0.3), Some(4.0), Some(5.0)]
+        ).unwrap();
+
+        let crossings = crate::util::threshold_crossings(&df, "t_2m:C", 0.0).unwrap();
+        assert_eq!(crossings.get_column_names(), &["lat", "lon", "validdate", "direction"]);
+        assert_eq!(crossings.height(), 2);
+        let dates: Vec<Option<&str>> = crossings.column("validdate").unwrap().utf8().unwrap().into_iter().collect();
+        assert_eq!(dates, vec![Some("t1"), Some("t4")]);
+        let directions: Vec<Option<&str>> = crossings.column("direction").unwrap().utf8().unwrap().into_iter().collect();
+        assert_eq!(directions, vec![Some("down"), Some("up")]);
+
+        // Missing value columns are reported
+        assert!(crate::util::threshold_crossings(&df, "precip_1h:mm", 0.0).is_err());
+    }
+
+    #[test]
+    fn check_validate_parameter() {
+        // valid
+        assert!(crate::util::validate_parameter("t_2m:C").is_ok());
+        assert!(crate::util::validate_parameter("wind_speed_10m:ms").is_ok());
+        assert!(crate::util::validate_parameter("t_2m:C-m0").is_ok());
+        assert!(crate::util::validate_parameters(&[String::from("t_2m:C"), String::from("precip_1h:mm")]).is_ok());
+        assert!(crate::util::validate_parameter("weather_symbol_1h").is_ok());
+
+        // missing unit
+        let err = crate::util::validate_parameter("t_2m").unwrap_err();
+        assert!(err.to_string().contains("'t_2m' has no unit"));
+        assert!(crate::util::validate_parameter("t_2m:").is_err());
+        assert!(crate::util::validate_parameter(":C").is_err());
+
+        // whitespace
+        let err = crate::util::validate_parameter("t_2m: C").unwrap_err();
+        assert!(err.to_string().contains("'t_2m: C' contains whitespace"));
+        assert!(crate::util::validate_parameter(" t_2m:C").is_err());
+        let err = crate::util::validate_parameters(&[String::from("t_2m:C"), String::from("precip_1h:mm ")]).unwrap_err();
+        assert!(err.to_string().contains("precip_1h:mm "));
+    }
+
+    #[test]
+    fn check_merge_seamless() {
+        // Stand-ins for the responses of the analysis and the forecast model, overlapping at 12:00.
+        let analysis = df!(
+            "validdate" => &["2022-05-17T10:00:00Z", "2022-05-17T11:00:00Z", "2022-05-17T12:00:00Z"],
+            "t_2m:C" => &[11.0, 12.0, 13.0]
+        ).unwrap();
+        let forecast = df!(
+            "validdate" => &["2022-05-17T12:00:00Z", "2022-05-17T13:00:00Z", "2022-05-17T14:00:00Z"],
+            "t_2m:C" => &[13.5, 14.0, 15.0]
+        ).unwrap();
+        let boundary = Utc.ymd(2022, 5, 17).and_hms(12, 0, 0);
+
+        let df = crate::util::merge_seamless(&analysis, &forecast, &boundary).unwrap();
+        assert_eq!(df.get_column_names(), &["validdate", "t_2m:C", "phase"]);
+        let phases: Vec<Option<&str>> = df.column("phase").unwrap().utf8().unwrap().into_iter().collect();
+        assert_eq!(phases, vec![Some("analysis"), Some("analysis"), Some("analysis"), Some("forecast"), Some("forecast")]);
+        let values: Vec<Option<f64>> = df.column("t_2m:C").unwrap().f64().unwrap().into_iter().collect();
+        assert_eq!(values, vec![Some(11.0), Some(12.0), Some(13.0), Some(14.0), Some(15.0)]);
+    }
+
+    #[test]
+    fn check_normalize_unit() {
+        // canonical units are kept
+        assert_eq!(crate::util::normalize_unit("t_2m:C").unwrap(), "t_2m:C");
+        assert_eq!(crate::util::normalize_unit("msl_pressure:hPa").unwrap(), "msl_pressure:hPa");
+        assert_eq!(crate::util::normalize_unit("precip_1h:mm").unwrap(), "precip_1h:mm");
+
+        // common variants are corrected
+        assert_eq!(crate::util::normalize_unit("t_2m:c").unwrap(), "t_2m:C");
+        assert_eq!(crate::util::normalize_unit("t_2m:Celsius").unwrap(), "t_2m:C");
+        assert_eq!(crate::util::normalize_unit("wind_speed_10m:m/s").unwrap(), "wind_speed_10m:ms");
+        assert_eq!(crate::util::normalize_unit("msl_pressure:HPA").unwrap(), "msl_pressure:hPa");
+        assert_eq!(crate::util::normalize_unit("relative_humidity_2m:%").unwrap(), "relative_humidity_2m:p");
+        assert_eq!(crate::util::normalize_unit("wind_dir_10m:Degrees").unwrap(), "wind_dir_10m:d");
+
+        // unrecognizable units and malformed parameters are reported
+        assert!(crate::util::normalize_unit("t_2m:Celcius").is_err());
+        assert!(crate::util::normalize_unit("t_2m:").is_err());
+        assert!(crate::util::normalize_unit("t_2m").is_err());
+        assert!(crate::util::normalize_unit(" t_2m:C").is_err());
+    }
+
+    #[test]
+    fn check_confidence() {
+        assert_eq!(crate::util::source_confidence(0.0), Some(1.0));
+        assert_eq!(crate::util::source_confidence(2.0), Some(0.5));
+        assert_eq!(crate::util::source_confidence(7.0), Some(0.25));
+        assert_eq!(crate::util::source_confidence(-1.0), None);
+        assert_eq!(crate::util::source_confidence(0.5), None);
+
+        // Response with values from the primary source and from two fallback levels
+        let df = df!(
+            "validdate" => &["t0", "t1", "t2", "t3", "t4"],
+            "t_2m:C" => &[11.2, 11.9, 12.4, 12.8, 13.1],
+            "source:idx" => &[Some(0), Some(0), Some(1), Some(3), None]
+        ).unwrap();
+        let df = crate::util::df_add_confidence(df, "source:idx").unwrap();
+        let confidence: Vec<Option<f64>> = df.column("confidence").unwrap().f64().unwrap().into_iter().collect();
+        assert_eq!(confidence, vec![Some(1.0), Some(1.0), Some(0.75), Some(0.25), None]);
+
+        assert!(crate::util::df_add_confidence(df, "precip_1h:mm").is_err());
+    }
+
+    #[test]
+    fn check_haversine_distance() {
+        let berlin = Point { lat: 52.5200, lon: 13.4050 };
+        let paris = Point { lat: 48.8566, lon: 2.3522 };
+        assert!((crate::util::haversine_km(&berlin, &paris) - 877.5).abs() < 1.0);
+        assert!((crate::util::haversine_km(&paris, &berlin) - 877.5).abs() < 1.0);
+        assert_eq!(crate::util::haversine_km(&berlin, &berlin), 0.0);
+        // One degree of latitude is about 111.2 km.
+        let north = Point { lat: 53.5200, lon: 13.4050 };
+        assert!((crate::util::haversine_km(&berlin, &north) - 111.19).abs() < 0.01);
+
+        // Nearest grid cells of a query for Berlin
+        let df = df!(
+            "lat" => &[Some(52.52), Some(48.8566), None],
+            "lon" => &[13.405, 2.3522, 13.405],
+            "t_2m:C" => &[11.2, 11.9, 12.4]
+        ).unwrap();
+        let df = crate::util::add_haversine_distance(df, &berlin, "lat", "lon").unwrap();
+        let distance: Vec<Option<f64>> = df.column("distance_km").unwrap().f64().unwrap().into_iter().collect();
+        assert_eq!(distance[0], Some(0.0));
+        assert!((distance[1].unwrap() - 877.5).abs() < 1.0);
+        assert_eq!(distance[2], None);
+
+        assert!(crate::util::add_haversine_distance(df, &berlin, "latitude", "lon").is_err());
+    }
+
+    #[test]
+    fn check_diurnal_cycle() {
+        // Three days of hourly values for two locations. The value is the hour of the day plus the
+        // day index, so the mean per hour is the hour plus one.
+        let start = Utc.ymd(2022, 5, 17).and_hms(0, 0, 0);
+        let mut lats = Vec::new();
+        let mut dates = Vec::new();
+        let mut values = Vec::new();
+        for lat in [47.42, 46.95] {
+            for i in 0..72 {
+                lats.push(lat);
+                dates.push((start + Duration::hours(i)).to_rfc3339());
+                values.push((i % 24 + i / 24) as f64);
+            }
+        }
+        let df = df!("lat" => &lats, "validdate" => &dates, "t_2m:C" => &values).unwrap();
+
+        let cycle = crate::util::diurnal_cycle(&df, "t_2m:C", &["lat"]).unwrap();
+        assert_eq!(cycle.get_column_names(), &["lat", "hour", "mean", "std"]);
+        assert_eq!(cycle.height(), 48);
+        let n_first: usize = cycle.column("lat").unwrap().f64().unwrap().into_iter().filter(|l| *l == Some(47.42)).count();
+        assert_eq!(n_first, 24);
+        let means: Vec<Option<f64>> = cycle.column("mean").unwrap().f64().unwrap().into_iter().collect();
+        assert_eq!(means[5], Some(6.0));
+        assert_eq!(means[24 + 23], Some(24.0));
+        assert_eq!(cycle.column("std").unwrap().f64().unwrap().get(0), Some(1.0));
+
+        // Missing columns are reported
+        assert!(crate::util::diurnal_cycle(&df, "precip_1h:mm", &["lat"]).is_err());
+    }
+
+    #[test]
+    fn check_sparkline() {
+        let df = df!(
+            "validdate" => &["t0", "t1", "t2", "t3", "t4"],
+            "t_2m:C" => &[Some(1.0), Some(4.0), None, Some(8.0), Some(2.5)]
+        ).unwrap();
+
+        let line = crate::util::sparkline(&df, "t_2m:C").unwrap();
+        assert_eq!(line.chars().count(), df.height());
+        assert_eq!(line, "▁▄ █▃");
+
+        // Unknown columns are reported as errors
+        assert!(crate::util::sparkline(&df, "precip_1h:mm").is_err());
     }
 }
\ No newline at end of file
@@ -0,0 +1,305 @@
+//! # Validation
+//! This module bundles offline checks of the query inputs. The checks do not require any network access
+//! and are meant to catch obviously invalid requests (e.g. coordinates out of range or a time series
+//! that ends before it starts) before they are sent to the API. Use [`crate::APIClient::check`] to run
+//! all checks on a query at once.
+
+use crate::connector_components::Format;
+use crate::location::{BBox, Point};
+use crate::util::{build_grid_query_specs_with_precision, build_ts_query_specs_with_precision, build_url, points_to_str, validate_parameter, TimeSeries};
+use std::fmt;
+
+/// Rough upper limit for the length of a request URL that is accepted by the API.
+pub const MAX_URL_LENGTH: usize = 8192;
+
+/// Describes a query in a uniform way such that all offline validations can be run on it.
+pub enum AnyQuery<'a> {
+    /// A time series query for a number of points (see ```query_time_series()```).
+    TimeSeries {
+        time_series: &'a TimeSeries,
+        parameters: &'a [String],
+        coordinates: &'a [Point],
+        optionals: &'a Option<Vec<String>>,
+    },
+    /// A grid query for a single point in time (see ```query_grid_unpivoted()```).
+    Grid {
+        timestamp: &'a chrono::DateTime<chrono::Utc>,
+        parameters: &'a [String],
+        bbox: &'a BBox,
+        optionals: &'a Option<Vec<String>>,
+    },
+    /// A grid query for a time series (see ```query_grid_unpivoted_time_series()```).
+    GridTimeSeries {
+        time_series: &'a TimeSeries,
+        parameters: &'a [String],
+        bbox: &'a BBox,
+        optionals: &'a Option<Vec<String>>,
+    },
+}
+
+/// A single problem found by the offline validation of a query.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ValidationIssue {
+    /// A latitude is not within [-90, 90].
+    LatitudeOutOfRange(f64),
+    /// A longitude is not within [-180, 180].
+    LongitudeOutOfRange(f64),
+    /// The same coordinate was requested more than once.
+    DuplicateCoordinate { lat: f64, lon: f64 },
+    /// No coordinates were specified.
+    NoCoordinates,
+    /// The minimum latitude of the bounding box is larger than the maximum latitude.
+    BBoxLatitudeOrder { lat_min: f64, lat_max: f64 },
+    /// The minimum longitude of the bounding box is larger than the maximum longitude.
+    BBoxLongitudeOrder { lon_min: f64, lon_max: f64 },
+    /// The resolution of the bounding box is not a positive number.
+    BBoxResolution { lat_res: f64, lon_res: f64 },
+    /// No parameters were specified.
+    NoParameters,
+    /// A parameter does not follow the "name:unit" grammar (e.g. "t_2m:C").
+    InvalidParameter(String),
+    /// The time series ends before it starts.
+    TimeSeriesOrder,
+    /// The time series has no (or a non-positive) timedelta.
+    TimeSeriesStep,
+    /// The resulting URL is most likely too long to be accepted.
+    UrlTooLong(usize),
+}
+
+impl fmt::Display for ValidationIssue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ValidationIssue::LatitudeOutOfRange(lat) => write!(f, "latitude {} is not within [-90, 90]", lat),
+            ValidationIssue::LongitudeOutOfRange(lon) => write!(f, "longitude {} is not within [-180, 180]", lon),
+            ValidationIssue::DuplicateCoordinate { lat, lon } => write!(f, "coordinate {},{} is requested more than once", lat, lon),
+            ValidationIssue::NoCoordinates => write!(f, "no coordinates specified"),
+            ValidationIssue::BBoxLatitudeOrder { lat_min, lat_max } => write!(f, "lat_min {} is larger than lat_max {}", lat_min, lat_max),
+            ValidationIssue::BBoxLongitudeOrder { lon_min, lon_max } => write!(f, "lon_min {} is larger than lon_max {}", lon_min, lon_max),
+            ValidationIssue::BBoxResolution { lat_res, lon_res } => write!(f, "resolution {},{} is not positive", lat_res, lon_res),
+            ValidationIssue::NoParameters => write!(f, "no parameters specified"),
+            ValidationIssue::InvalidParameter(p) => write!(f, "parameter '{}' is not of the form 'name:unit'", p),
+            ValidationIssue::TimeSeriesOrder => write!(f, "time series ends before it starts"),
+            ValidationIssue::TimeSeriesStep => write!(f, "time series needs a positive timedelta"),
+            ValidationIssue::UrlTooLong(n) => write!(f, "URL with {} characters exceeds {} characters", n, MAX_URL_LENGTH),
+        }
+    }
+}
+
+/// Checks if a parameter follows the "name:unit" grammar of the API (e.g. "t_2m:C" or "wind_speed_10m:ms").
+/// See [`validate_parameter`] for the details of the grammar.
+///
+/// # Arguments
+///
+/// * `parameter` - The name of the parameter (e.g. "t_2m:C").
+///
+pub fn is_valid_parameter(parameter: &str) -> bool {
+    validate_parameter(parameter).is_ok()
+}
+
+/// Checks the coordinates of a number of points (ranges and duplicates).
+pub fn check_points(coordinates: &[Point]) -> Vec<ValidationIssue> {
+    let mut issues = Vec::new();
+    if coordinates.is_empty() {
+        issues.push(ValidationIssue::NoCoordinates);
+    }
+    for (i, point) in coordinates.iter().enumerate() {
+        issues.extend(check_lat_lon(point.lat, point.lon));
+        let is_duplicate = coordinates[..i]
+            .iter()
+            .any(|p| p.lat == point.lat && p.lon == point.lon);
+        if is_duplicate {
+            issues.push(ValidationIssue::DuplicateCoordinate { lat: point.lat, lon: point.lon });
+        }
+    }
+    issues
+}
+
+/// Checks the coordinates, the ordering and the resolution of a bounding box.
+pub fn check_bbox(bbox: &BBox) -> Vec<ValidationIssue> {
+    let mut issues = Vec::new();
+    issues.extend(check_lat_lon(bbox.lat_min, bbox.lon_min));
+    issues.extend(check_lat_lon(bbox.lat_max, bbox.lon_max));
+    if bbox.lat_min > bbox.lat_max {
+        issues.push(ValidationIssue::BBoxLatitudeOrder { lat_min: bbox.lat_min, lat_max: bbox.lat_max });
+    }
+    if bbox.lon_min > bbox.lon_max {
+        issues.push(ValidationIssue::BBoxLongitudeOrder { lon_min: bbox.lon_min, lon_max: bbox.lon_max });
+    }
+    // NaN values are caught as well, since the comparison fails.
+    if !(bbox.lat_res > 0.0 && bbox.lon_res > 0.0) {
+        issues.push(ValidationIssue::BBoxResolution { lat_res: bbox.lat_res, lon_res: bbox.lon_res });
+    }
+    issues
+}
+
+/// Checks the parameter names against the "name:unit" grammar.
+pub fn check_parameters(parameters: &[String]) -> Vec<ValidationIssue> {
+    let mut issues = Vec::new();
+    if parameters.is_empty() {
+        issues.push(ValidationIssue::NoParameters);
+    }
+    for parameter in parameters {
+        if !is_valid_parameter(parameter) {
+            issues.push(ValidationIssue::InvalidParameter(parameter.clone()));
+        }
+    }
+    issues
+}
+
+/// Checks that the time series ends after it starts and has a positive timedelta.
+pub fn check_time_series(time_series: &TimeSeries) -> Vec<ValidationIssue> {
+    let mut issues = Vec::new();
+    if time_series.end < time_series.start {
+        issues.push(ValidationIssue::TimeSeriesOrder);
+    }
+    match time_series.timedelta {
+        Some(timedelta) if timedelta > chrono::Duration::zero() => (),
+        _ => issues.push(ValidationIssue::TimeSeriesStep),
+    }
+    issues
+}
+
+/// Runs all offline validations on a query and returns every issue that was found.
+///
+/// # Arguments
+///
+/// * `query` - The query to be validated.
+///
+pub async fn check_query(query: &AnyQuery<'_>) -> Vec<ValidationIssue> {
+    check_query_with_precision(query, chrono::SecondsFormat::AutoSi).await
+}
+
+/// Same as [`check_query`], but measures the length of the URL with the dates rendered in the given
+/// precision (see [`crate::util::format_timestamp`]), as done by a client with
+/// ```with_timestamp_precision()```.
+///
+pub async fn check_query_with_precision(query: &AnyQuery<'_>, precision: chrono::SecondsFormat) -> Vec<ValidationIssue> {
+    let mut issues = Vec::new();
+    let query_specs = match query {
+        AnyQuery::TimeSeries { time_series, parameters, coordinates, optionals } => {
+            issues.extend(check_time_series(time_series));
+            issues.extend(check_parameters(parameters));
+            issues.extend(check_points(coordinates));
+            // The URL can only be built for a time series with a timedelta.
+            let coords_str = points_to_str(coordinates).await;
+            build_ts_query_specs_with_precision(time_series, parameters, &coords_str, optionals, Format::Csv, precision)
+                .await
+                .ok()
+        }
+        AnyQuery::Grid { timestamp, parameters, bbox, optionals } => {
+            issues.extend(check_parameters(parameters));
+            issues.extend(check_bbox(bbox));
+            let params = parameters.join(",");
+            Some(build_grid_query_specs_with_precision(timestamp, &params, &bbox.to_string(), optionals, Format::Csv, precision).await)
+        }
+        AnyQuery::GridTimeSeries { time_series, parameters, bbox, optionals } => {
+            issues.extend(check_time_series(time_series));
+            issues.extend(check_parameters(parameters));
+            issues.extend(check_bbox(bbox));
+            build_ts_query_specs_with_precision(time_series, parameters, &bbox.to_string(), optionals, Format::Csv, precision)
+                .await
+                .ok()
+        }
+    };
+
+    if let Some(query_specs) = query_specs {
+        // A URL that cannot be parsed has no meaningful length, the query would fail later anyway.
+        if let Ok(url) = build_url(&query_specs).await {
+            let n = url.as_str().len();
+            if n > MAX_URL_LENGTH {
+                issues.push(ValidationIssue::UrlTooLong(n));
+            }
+        }
+    }
+
+    issues
+}
+
+fn check_lat_lon(lat: f64, lon: f64) -> Vec<ValidationIssue> {
+    let mut issues = Vec::new();
+    if !(-90.0..=90.0).contains(&lat) {
+        issues.push(ValidationIssue::LatitudeOutOfRange(lat));
+    }
+    if !(-180.0..=180.0).contains(&lon) {
+        issues.push(ValidationIssue::LongitudeOutOfRange(lon));
+    }
+    issues
+}
+
+#[cfg(test)]
+mod tests {
+
+    use chrono::{Duration, TimeZone, Utc};
+    use crate::location::{BBox, Point};
+    use crate::util::TimeSeries;
+    use crate::validation::{AnyQuery, ValidationIssue};
+    use crate::APIClient;
+
+    #[tokio::test]
+    async fn check_reports_all_issues() {
+        let start_date = Utc.ymd(2022, 5, 17).and_hms(12, 0, 0);
+        let time_series = TimeSeries {
+            start: start_date,
+            end: start_date - Duration::days(1),
+            timedelta: None,
+        };
+        let parameters = vec![String::from("t_2m:C"), String::from("t_2m")];
+        let coords = vec![
+            Point { lat: 47.423, lon: 9.370 },
+            Point { lat: 91.0, lon: 9.370 },
+            Point { lat: 47.423, lon: 9.370 },
+        ];
+        let query = AnyQuery::TimeSeries {
+            time_series: &time_series,
+            parameters: &parameters,
+            coordinates: &coords,
+            optionals: &None,
+        };
+
+        let client = APIClient::new("test_user", "test_password", 10);
+        let issues = client.check(&query).await.unwrap_err();
+        assert_eq!(issues.len(), 5);
+        assert!(issues.contains(&ValidationIssue::TimeSeriesOrder));
+        assert!(issues.contains(&ValidationIssue::TimeSeriesStep));
+        assert!(issues.contains(&ValidationIssue::InvalidParameter(String::from("t_2m"))));
+        assert!(issues.contains(&ValidationIssue::LatitudeOutOfRange(91.0)));
+        assert!(issues.contains(&ValidationIssue::DuplicateCoordinate { lat: 47.423, lon: 9.370 }));
+    }
+
+    #[tokio::test]
+    async fn check_accepts_valid_grid() {
+        let timestamp = Utc.ymd(2022, 5, 17).and_hms(12, 0, 0);
+        let parameters = vec![String::from("t_2m:C"), String::from("precip_1h:mm")];
+        let bbox = BBox {
+            lat_min: 52.40,
+            lat_max: 52.50,
+            lon_min: 13.40,
+            lon_max: 13.50,
+            lat_res: 0.05,
+            lon_res: 0.05
+        };
+        let query = AnyQuery::Grid {
+            timestamp: &timestamp,
+            parameters: &parameters,
+            bbox: &bbox,
+            optionals: &None,
+        };
+
+        let client = APIClient::new("test_user", "test_password", 10);
+        assert!(client.check(&query).await.is_ok());
+
+        // Swapped latitudes and a very long parameter list
+        let bbox = BBox { lat_min: 52.50, lat_max: 52.40, ..bbox };
+        let parameters = vec![String::from("t_2m:C"); 2000];
+        let query = AnyQuery::Grid {
+            timestamp: &timestamp,
+            parameters: &parameters,
+            bbox: &bbox,
+            optionals: &None,
+        };
+        let issues = client.check(&query).await.unwrap_err();
+        assert_eq!(issues.len(), 2);
+        assert!(issues.contains(&ValidationIssue::BBoxLatitudeOrder { lat_min: 52.50, lat_max: 52.40 }));
+        assert!(matches!(issues[1], ValidationIssue::UrlTooLong(_)));
+    }
+}
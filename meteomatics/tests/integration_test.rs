@@ -1,8 +1,10 @@
 use meteomatics::APIClient;
-use chrono::{Duration, Utc, prelude::*};
+use chrono::{Duration, Utc, TimeZone};
 use dotenv::dotenv;
 use std::env;
 use meteomatics::{Point, BBox, TimeSeries};
+use meteomatics::connector_components::Interpolation;
+use meteomatics::util::{GridTime, frames_approx_equal};
 use polars::prelude::*;
 use std::io::Cursor;
 use std::fs;
@@ -50,9 +52,7 @@ async fn call_query_time_series_with_options() {
     };
 
     // Create Parameters
-    let mut parameters = Vec::new();
-    parameters.push(String::from("t_2m:C"));
-    parameters.push(String::from("precip_1h:mm"));
+    let parameters = vec![String::from("t_2m:C"), String::from("precip_1h:mm")];
 
     // Create Locations
     let p1: Point = Point { lat: 52.520551, lon: 13.461804};
@@ -60,9 +60,7 @@ async fn call_query_time_series_with_options() {
     let coords: Vec<Point> = vec![p1, p2];
 
     // Create Optionals
-    let mut optionals = Vec::new();
-    optionals.push(String::from("source=mix"));
-    optionals.push(String::from("calibrated=true"));
+    let optionals = vec![String::from("source=mix"), String::from("calibrated=true")];
 
     // Call endpoint
     let df_q = meteomatics_connector
@@ -73,7 +71,86 @@ async fn call_query_time_series_with_options() {
 
     println!("Rust result: {:?}", df_q);
     println!("Python result: {:?}", df_s);
-    assert!(df_s.frame_equal(&df_q));
+    assert!(frames_approx_equal(&df_s, &df_q, 1e-6));
+}
+
+#[tokio::test]
+async fn call_query_time_series_with_interpolation() {
+    // Credentials
+    dotenv().ok();
+    let api_key: String = env::var("METEOMATICS_PW").unwrap();
+    let api_user: String = env::var("METEOMATICS_USER").unwrap();
+
+    // Create API connector
+    let meteomatics_connector = APIClient::new(
+        &api_user,
+        &api_key,
+        10,
+    );
+
+    // Create time series
+    let time_series_start = Utc.ymd(1989, 11, 9).and_hms_micro(18, 0, 0, 0);
+    let time_series = TimeSeries{ 
+        start: time_series_start, 
+        end: time_series_start + Duration::days(1), 
+        timedelta: Option::from(Duration::hours(12)) 
+    };
+
+    // Create Parameters
+    let parameters = vec![String::from("t_2m:C")];
+
+    // Create Locations
+    let coords = vec![Point { lat: 52.520551, lon: 13.461804}];
+
+    // Create Optionals
+    let optionals = vec![Interpolation::GradientInterpolation.into()];
+
+    // Call endpoint
+    let df_q = meteomatics_connector
+        .query_time_series(
+            &time_series, &parameters, &coords, &Option::from(optionals)
+        )
+        .await.unwrap();
+
+    println!("Rust result: {:?}", df_q);
+    assert_eq!(df_q.height(), 3);
+}
+
+#[tokio::test]
+async fn query_time_series_dates_one_point_one_param() {
+    // Credentials
+    dotenv().ok();
+    let api_key: String = env::var("METEOMATICS_PW").unwrap();
+    let api_user: String = env::var("METEOMATICS_USER").unwrap();
+
+    // Create API connector
+    let meteomatics_connector = APIClient::new(
+        &api_user,
+        &api_key,
+        10,
+    );
+
+    // Create irregular dates (the same dates as the regular time series in the other tests)
+    let dates = vec![
+        Utc.ymd(1989, 11, 9).and_hms_micro(18, 0, 0, 0),
+        Utc.ymd(1989, 11, 10).and_hms_micro(18, 0, 0, 0),
+    ];
+
+    // Create Parameters
+    let parameters = vec![String::from("t_2m:C")];
+
+    // Create Locations
+    let coords = vec![Point { lat: 52.520551, lon: 13.461804}];
+
+    // Call endpoint
+    let df_q = meteomatics_connector
+        .query_time_series_dates(&dates, &parameters, &coords, &None)
+        .await
+        .unwrap();
+
+    println!("Rust result: {:?}", df_q);
+    assert_eq!(df_q.height(), 2);
+    assert_eq!(df_q.get_column_names(), &["lat", "lon", "validdate", "t_2m:C"]);
 }
 
 #[tokio::test]
@@ -117,9 +194,7 @@ async fn call_query_time_series_without_options() {
     };
 
     // Create Parameters
-    let mut parameters = Vec::new();
-    parameters.push(String::from("t_2m:C"));
-    parameters.push(String::from("precip_1h:mm"));
+    let parameters = vec![String::from("t_2m:C"), String::from("precip_1h:mm")];
 
     // Create Locations
     let p1: Point = Point { lat: 52.520551, lon: 13.461804};
@@ -133,7 +208,7 @@ async fn call_query_time_series_without_options() {
 
     println!("Rust result: {:?}", df_q);
     println!("Python result: {:?}", df_s);
-    assert!(df_s.frame_equal(&df_q));
+    assert!(frames_approx_equal(&df_s, &df_q, 1e-6));
 }
 
 #[tokio::test]
@@ -187,7 +262,7 @@ async fn query_time_series_one_point_one_param() {
         .unwrap();
     println!("Rust result: {:?}", df_q);
     println!("Python result: {:?}", df_s);
-    assert!(df_s.frame_equal(&df_q));
+    assert!(frames_approx_equal(&df_s, &df_q, 1e-6));
 }
 
 #[tokio::test]
@@ -241,7 +316,7 @@ async fn query_time_series_one_point_two_param() {
         .unwrap();
     println!("Rust result: {:?}", df_q);
     println!("Python result: {:?}", df_s);
-    assert!(df_s.frame_equal(&df_q));
+    assert!(frames_approx_equal(&df_s, &df_q, 1e-6));
 }
 
 #[tokio::test]
@@ -299,7 +374,7 @@ async fn query_time_series_two_point_two_param() {
         .unwrap();
     println!("Rust result: {:?}", df_q);
     println!("Python result: {:?}", df_s);
-    assert!(df_s.frame_equal(&df_q));
+    assert!(frames_approx_equal(&df_s, &df_q, 1e-6));
 }
 
 #[tokio::test]
@@ -352,7 +427,7 @@ async fn query_time_series_one_postal_one_param() {
         .unwrap();
     println!("Rust result: {:?}", df_q);
     println!("Python result: {:?}", df_s);
-    assert!(df_s.frame_equal(&df_q));
+    assert!(frames_approx_equal(&df_s, &df_q, 1e-6));
 }
 
 #[tokio::test]
@@ -408,13 +483,12 @@ async fn query_time_series_two_postal_two_param() {
         .unwrap();
     println!("Rust result: {:?}", df_q);
     println!("Python result: {:?}", df_s);
-    assert!(df_s.frame_equal(&df_q));
+    assert!(frames_approx_equal(&df_s, &df_q, 1e-6));
 }
 
 #[tokio::test]
 async fn query_grid_pivoted() {
-    // This is a bit hacked, because the python connetor changes 'data' to 'lat'
-    let s = r#"data,13.4,13.45,13.5
+    let s = r#"lat,13.4,13.45,13.5
     52.5,6.8,6.9,6.9
     52.45,6.8,6.8,6.9
     52.4,6.8,6.9,6.9
@@ -463,7 +537,58 @@ async fn query_grid_pivoted() {
         .unwrap();
     println!("Rust result: {:?}", df_q);
     println!("Python result: {:?}", df_s);
-    assert!(df_s.frame_equal(&df_q));
+    assert!(frames_approx_equal(&df_s, &df_q, 1e-6));
+}
+
+#[tokio::test]
+async fn query_grid_pivoted_time_series() {
+    // Query using rust connector
+    // Credentials
+    dotenv().ok();
+    let api_key: String = env::var("METEOMATICS_PW").unwrap();
+    let api_user: String = env::var("METEOMATICS_USER").unwrap();
+
+    // Create API connector
+    let meteomatics_connector = APIClient::new(
+        &api_user,
+        &api_key,
+        10,
+    );
+
+    // Create time series with two timestamps
+    let start_date = Utc.ymd(1989, 11, 9).and_hms_micro(18, 0, 0, 0);
+    let time_series = TimeSeries {
+        start: start_date,
+        end: start_date + Duration::hours(12),
+        timedelta: Option::from(Duration::hours(12))
+    };
+
+    // Create Parameters
+    let parameter = String::from("t_2m:C");
+
+    // Create Location
+    let bbox = BBox {
+        lat_min: 52.40,
+        lat_max: 52.50,
+        lon_min: 13.40,
+        lon_max: 13.50,
+        lat_res: 0.05,
+        lon_res: 0.05
+    };
+
+    // Call endpoint
+    let grids = meteomatics_connector
+        .query_grid_pivoted_time_series(&time_series, &parameter, &bbox, &None)
+        .await
+        .unwrap();
+    println!("Rust result: {:?}", grids);
+    assert_eq!(grids.len(), 2);
+    assert_eq!(grids[0].0, start_date);
+    assert_eq!(grids[1].0, start_date + Duration::hours(12));
+    for (_, df) in grids {
+        assert_eq!(df.shape(), (3, 4));
+        assert_eq!(df.get_column_names()[0], "lat");
+    }
 }
 
 #[tokio::test]
@@ -526,7 +651,83 @@ async fn query_grid_unpivoted() {
         .unwrap();
     println!("Rust result: {:?}", df_q);
     println!("Python result: {:?}", df_s);
-    assert!(df_s.frame_equal(&df_q));
+    assert!(frames_approx_equal(&df_s, &df_q, 1e-6));
+}
+
+#[tokio::test]
+async fn query_grid_unpivoted_dates() {
+    // directly downloaded from the API
+    // https://api.meteomatics.com/1989-11-09T18:00:00.000Z--1989-11-10T18:00:00.000Z:PT12H/t_2m:C,precip_1h:mm/52.50,13.40_52.40,13.50:0.05,0.05/csv?model=mix
+    // (only the rows of the two requested dates)
+    let s = r#"lat;lon;validdate;t_2m:C
+    52.4;13.4;1989-11-09T18:00:00Z;6.8
+    52.4;13.4;1989-11-10T18:00:00Z;5.4
+    52.4;13.45;1989-11-09T18:00:00Z;6.9
+    52.4;13.45;1989-11-10T18:00:00Z;5.3
+    52.4;13.5;1989-11-09T18:00:00Z;6.9
+    52.4;13.5;1989-11-10T18:00:00Z;5.3
+    52.45;13.4;1989-11-09T18:00:00Z;6.8
+    52.45;13.4;1989-11-10T18:00:00Z;5.4
+    52.45;13.45;1989-11-09T18:00:00Z;6.8
+    52.45;13.45;1989-11-10T18:00:00Z;5.3
+    52.45;13.5;1989-11-09T18:00:00Z;6.9
+    52.45;13.5;1989-11-10T18:00:00Z;5.3
+    52.5;13.4;1989-11-09T18:00:00Z;6.8
+    52.5;13.4;1989-11-10T18:00:00Z;5.4
+    52.5;13.45;1989-11-09T18:00:00Z;6.9
+    52.5;13.45;1989-11-10T18:00:00Z;5.3
+    52.5;13.5;1989-11-09T18:00:00Z;6.9
+    52.5;13.5;1989-11-10T18:00:00Z;5.3
+    "#;
+    let file = Cursor::new(s);
+    let df_s = CsvReader::new(file)
+        .infer_schema(Some(100))
+        .has_header(true)
+        .with_delimiter(b';')
+        .with_ignore_parser_errors(true)
+        .finish()
+        .unwrap();
+
+    // Query using rust connector
+    // Credentials
+    dotenv().ok();
+    let api_key: String = env::var("METEOMATICS_PW").unwrap();
+    let api_user: String = env::var("METEOMATICS_USER").unwrap();
+
+    // Create API connector
+    let meteomatics_connector = APIClient::new(
+        &api_user,
+        &api_key,
+        10,
+    );
+
+    // Create time information
+    let dates = vec![
+        Utc.ymd(1989, 11, 9).and_hms_micro(18, 0, 0, 0),
+        Utc.ymd(1989, 11, 10).and_hms_micro(18, 0, 0, 0),
+    ];
+
+    // Create Parameters
+    let parameters = vec![String::from("t_2m:C")];
+
+    // Create Location
+    let bbox = BBox {
+        lat_min: 52.40,
+        lat_max: 52.50,
+        lon_min: 13.40,
+        lon_max: 13.50,
+        lat_res: 0.05,
+        lon_res: 0.05
+    };
+
+    // Call endpoint
+    let df_q = meteomatics_connector
+        .query_grid_unpivoted_dates(&dates, &parameters, &bbox, &Some(vec![String::from("model=mix")]))
+        .await
+        .unwrap();
+    println!("Rust result: {:?}", df_q);
+    println!("Python result: {:?}", df_s);
+    assert!(frames_approx_equal(&df_s, &df_q, 1e-6));
 }
 
 #[tokio::test]
@@ -614,7 +815,102 @@ async fn query_grid_unpivoted_time_series() {
         .unwrap();
     println!("Rust result: {:?}", df_q);
     println!("Python result: {:?}", df_s);
-    assert!(df_s.frame_equal(&df_q));
+    assert!(frames_approx_equal(&df_s, &df_q, 1e-6));
+}
+
+#[tokio::test]
+async fn query_grid_bytes_png() {
+    // Credentials
+    dotenv().ok();
+    let api_key: String = env::var("METEOMATICS_PW").unwrap();
+    let api_user: String = env::var("METEOMATICS_USER").unwrap();
+    
+    // Create API connector
+    let meteomatics_connector = APIClient::new(
+        &api_user,
+        &api_key,
+        10,
+    );
+
+    // Create time information
+    let date = Utc.ymd(1989, 11, 9).and_hms_micro(18, 0, 0, 0);
+
+    // Create Parameters
+    let parameter = String::from("t_2m:C");
+
+    // Create Location
+    let bbox = BBox {
+        lat_min: 45.8179716,
+        lat_max: 47.8084648,
+        lon_min: 5.9559113,
+        lon_max: 10.4922941,
+        lat_res: 0.01,
+        lon_res: 0.01
+    };
+
+    // Call endpoint
+    let png = meteomatics_connector
+        .query_grid_bytes(GridTime::Timestamp(&date), &parameter, &bbox, "png", &None)
+        .await
+        .unwrap();
+    assert!(!png.is_empty());
+    // PNG signature
+    assert_eq!(&png[..8], &[0x89, b'P', b'N', b'G', 0x0d, 0x0a, 0x1a, 0x0a]);
+}
+
+#[tokio::test]
+async fn query_netcdf_multi() {
+    // Credentials
+    dotenv().ok();
+    let api_key: String = env::var("METEOMATICS_PW").unwrap();
+    let api_user: String = env::var("METEOMATICS_USER").unwrap();
+
+    // Create API connector
+    let meteomatics_connector = APIClient::new(
+        &api_user,
+        &api_key,
+        10,
+    );
+
+    // Create time series
+    let time_series_start = Utc.ymd(1989, 11, 9).and_hms_micro(18, 0, 0, 0);
+    let time_series = TimeSeries{
+        start: time_series_start,
+        end: time_series_start + Duration::days(1),
+        timedelta: Option::from(Duration::hours(12))
+    };
+
+    // Create Parameters
+    let parameters = vec![String::from("t_2m:C"), String::from("precip_1h:mm")];
+
+    // Create Location
+    let bbox = BBox {
+        lat_min: 52.40,
+        lat_max: 52.50,
+        lon_min: 13.40,
+        lon_max: 13.50,
+        lat_res: 0.05,
+        lon_res: 0.05
+    };
+
+    // Call endpoint
+    let dir = "tests/netcdf_multi";
+    let files = meteomatics_connector
+        .query_netcdf_multi(&time_series, &parameters, &bbox, dir, &None)
+        .await
+        .unwrap();
+
+    assert_eq!(files, vec![
+        Path::new(dir).join("t_2m_C.nc"),
+        Path::new(dir).join("precip_1h_mm.nc"),
+    ]);
+    for file in &files {
+        let size = fs::File::open(file).unwrap().metadata().unwrap().len();
+        assert!(size > 0);
+    }
+
+    // Remove the files
+    fs::remove_dir_all(dir).unwrap();
 }
 
 #[tokio::test]
@@ -685,12 +981,69 @@ async fn query_netcdf() {
     // Remove the file    
     let dir: &Path = Path::new(&file_name).parent().unwrap();
     fs::remove_file(&file_name).unwrap();
-    fs::remove_dir_all(&dir).unwrap();
+    fs::remove_dir_all(dir).unwrap();
     // Check if the file and the directory were removed.
     assert!(!Path::new(&file_name).exists());
     assert!(!Path::new(&dir).exists());
 }
 
+#[tokio::test]
+async fn query_grib() {
+    // Query using rust connector
+    // Credentials
+    dotenv().ok();
+    let api_key: String = env::var("METEOMATICS_PW").unwrap();
+    let api_user: String = env::var("METEOMATICS_USER").unwrap();
+
+    // Create API connector
+    let meteomatics_connector = APIClient::new(
+        &api_user,
+        &api_key,
+        10,
+    );
+
+    // Create time series
+    let time_series_start = Utc.ymd(1989, 11, 9).and_hms_micro(18, 0, 0, 0);
+    let time_series = TimeSeries{
+        start: time_series_start,
+        end: time_series_start + Duration::days(1),
+        timedelta: Option::from(Duration::hours(12))
+    };
+
+    // Create Parameters
+    let parameter = String::from("t_2m:C");
+
+    // Create Location
+    let bbox = BBox {
+        lat_min: 52.40,
+        lat_max: 52.50,
+        lon_min: 13.40,
+        lon_max: 13.50,
+        lat_res: 0.05,
+        lon_res: 0.05
+    };
+
+    // Create file name
+    let file_name = String::from("tests/grib/my_grib.grb2");
+
+    // Call endpoint
+    meteomatics_connector
+        .query_grib(
+            &time_series, &parameter, &bbox, &file_name, &None
+        )
+        .await
+        .unwrap();
+
+    // Every GRIB message starts with the magic bytes "GRIB".
+    let content = fs::read(&file_name).unwrap();
+    assert!(content.starts_with(b"GRIB"));
+
+    // Remove the file
+    let dir: &Path = Path::new(&file_name).parent().unwrap();
+    fs::remove_dir_all(dir).unwrap();
+    assert!(!Path::new(&dir).exists());
+}
+
 #[tokio::test]
 async fn query_png() {
     // Query using rust connector
@@ -744,7 +1097,7 @@ async fn query_png() {
     // Remove the file    
     let dir: &Path = Path::new(&file_name).parent().unwrap();
     fs::remove_file(&file_name).unwrap();
-    fs::remove_dir_all(&dir).unwrap();
+    fs::remove_dir_all(dir).unwrap();
     // Check if the file and the directory were removed.
     assert!(!Path::new(&file_name).exists());
     assert!(!Path::new(&dir).exists());
@@ -810,7 +1163,7 @@ async fn query_grid_png_timeseries() {
     // Remove the file    
     let dir: &Path = Path::new(&file_name).parent().unwrap();
     fs::remove_file(&file_name).unwrap();
-    fs::remove_dir_all(&dir).unwrap();
+    fs::remove_dir_all(dir).unwrap();
     // Check if the file and the directory were removed.
     assert!(!Path::new(&file_name).exists());
     assert!(!Path::new(&dir).exists());
@@ -835,6 +1188,26 @@ async fn query_user_features(){
     assert_eq!(env::var("METEOMATICS_USER").unwrap(), ustats.stats.username);
 }
 
+#[tokio::test]
+async fn query_ensemble_size(){
+    // Query using rust connector
+    // Credentials
+    dotenv().ok();
+    let api_key: String = env::var("METEOMATICS_PW").unwrap();
+    let api_user: String = env::var("METEOMATICS_USER").unwrap();
+
+    // Create API connector
+    let meteomatics_connector = APIClient::new(
+        &api_user,
+        &api_key,
+        10,
+    );
+
+    // ECMWF ENS consists of the control run and 50 perturbed members.
+    let n_members = meteomatics_connector.query_ensemble_size("ecmwf-ens").await.unwrap();
+    assert_eq!(n_members, 51);
+}
+
 #[tokio::test]
 async fn query_lightning(){
     // Query using rust connector